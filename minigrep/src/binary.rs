@@ -0,0 +1,66 @@
+use std::io::{self, BufRead};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum BinaryFiles {
+	Binary,       // search it, but only say whether it matched
+	Text,         // search and print it like any other file
+	WithoutMatch, // assume it doesn't match and don't even look
+}
+
+impl BinaryFiles {
+	pub fn parse(value: &str) -> Option<BinaryFiles> {
+		match value {
+			"binary" => Some(BinaryFiles::Binary),
+			"text" => Some(BinaryFiles::Text),
+			"without-match" => Some(BinaryFiles::WithoutMatch),
+			_ => None,
+		}
+	}
+}
+
+pub fn sniff(reader: &mut impl BufRead) -> io::Result<bool> {
+	/* text files practically never hold a NUL byte, while executables and
+	 * images almost always have one near the start. fill_buf only peeks at
+	 * the first chunk the reader has buffered, so nothing is consumed and
+	 * the search still starts at byte 0.
+	 */
+	let chunk = reader.fill_buf()?;
+	Ok(chunk.contains(&0))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::BufReader;
+	
+	#[test]
+	fn parse_policies() {
+		assert_eq!(Some(BinaryFiles::Binary), BinaryFiles::parse("binary"));
+		assert_eq!(Some(BinaryFiles::Text), BinaryFiles::parse("text"));
+		assert_eq!(Some(BinaryFiles::WithoutMatch), BinaryFiles::parse("without-match"));
+		assert_eq!(None, BinaryFiles::parse("skip"));
+	}
+	
+	#[test]
+	fn sniff_leaves_the_input_alone() {
+		let mut reader = BufReader::new(&b"\x7fELF\x02\x01\x01\x00\x00frog\n"[..]);
+		assert!(sniff(&mut reader).unwrap());
+		
+		// the peeked bytes are all still there to be searched
+		let mut line = Vec::new();
+		reader.read_until(b'\n', &mut line).unwrap();
+		assert!(line.starts_with(b"\x7fELF"));
+		
+		let mut reader = BufReader::new(&b"I'm nobody! Who are you?\n"[..]);
+		assert!(!sniff(&mut reader).unwrap());
+		assert!(!sniff(&mut BufReader::new(io::empty())).unwrap());
+	}
+	
+	#[test]
+	fn sniff_only_looks_at_the_first_chunk() {
+		let mut contents = vec![b'a'; 16];
+		contents.push(0);
+		let mut reader = BufReader::with_capacity(8, &contents[..]);
+		assert!(!sniff(&mut reader).unwrap());
+	}
+}
@@ -0,0 +1,63 @@
+use std::io::{self, IsTerminal};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorChoice {
+	Auto,
+	Always,
+	Never,
+}
+
+impl ColorChoice {
+	pub fn parse(value: &str) -> Option<ColorChoice> {
+		match value {
+			"auto" | "tty" | "if-tty" => Some(ColorChoice::Auto),
+			"always" | "yes" | "force" => Some(ColorChoice::Always),
+			"never" | "no" | "none" => Some(ColorChoice::Never),
+			_ => None,
+		}
+	}
+	
+	pub fn resolve(self) -> ColorChoice {
+		// auto means color only when a person is looking at the output
+		match self {
+			ColorChoice::Auto if io::stdout().is_terminal() => ColorChoice::Always,
+			ColorChoice::Auto => ColorChoice::Never,
+			choice => choice,
+		}
+	}
+}
+
+// SGR codes, the same defaults GNU grep uses
+pub const MATCH: &str = "01;31"; // bold red
+pub const FILE_NAME: &str = "35"; // magenta
+pub const LINE_NUMBER: &str = "32"; // green
+pub const BYTE_OFFSET: &str = "32"; // green
+pub const SEPARATOR: &str = "36"; // cyan
+
+pub fn paint(enabled: bool, code: &str, text: &str) -> String {
+	if enabled && !text.is_empty() {
+		format!("\x1b[{code}m{text}\x1b[0m")
+	} else {
+		text.to_string()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn parse_choices() {
+		assert_eq!(Some(ColorChoice::Always), ColorChoice::parse("always"));
+		assert_eq!(Some(ColorChoice::Never), ColorChoice::parse("never"));
+		assert_eq!(Some(ColorChoice::Auto), ColorChoice::parse("auto"));
+		assert_eq!(None, ColorChoice::parse("sometimes"));
+	}
+	
+	#[test]
+	fn paint_only_when_enabled() {
+		assert_eq!("\x1b[35mpoem.txt\x1b[0m", paint(true, FILE_NAME, "poem.txt"));
+		assert_eq!("poem.txt", paint(false, FILE_NAME, "poem.txt"));
+		assert_eq!("", paint(true, MATCH, ""));
+	}
+}
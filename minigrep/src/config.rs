@@ -0,0 +1,1125 @@
+use std::env; // for environment variables
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+
+use encoding_rs::Encoding;
+use regex_syntax::ast::{self, Ast, ClassSetItem, Visitor};
+
+use crate::defaults::{self, Value};
+use crate::encoding;
+use crate::{BinaryFiles, ColorChoice};
+
+pub struct Config {
+	pub patterns: Vec<String>, // a line matches if any one of them does
+	pub file_paths: Vec<String>,
+	pub ignore_case: bool,
+	pub smart_case: bool,
+	pub verbose: bool,
+	pub count: bool,
+	pub files_with_matches: bool,
+	pub files_without_match: bool,
+	pub max_count: Option<usize>,
+	pub quiet: bool,
+	pub regexp: bool,
+	pub fuzzy: Option<usize>,
+	pub recursive: bool,
+	pub follow: bool,
+	pub search_zip: bool, // decompress compressed FILEs before searching
+	pub line_number: bool,
+	pub byte_offset: bool,
+	pub before_context: usize,
+	pub after_context: usize,
+	pub invert_match: bool,
+	pub word_regexp: bool,
+	pub line_regexp: bool,
+	pub multiline: bool,
+	pub threads: usize,
+	pub color: ColorChoice,
+	pub json: bool,
+	pub replace: Option<String>,
+	pub in_place: bool,
+	pub backup_suffix: Option<String>,
+	pub include: Vec<String>,
+	pub exclude: Vec<String>,
+	pub no_ignore: bool,
+	pub hidden: bool,
+	pub binary_files: BinaryFiles,
+	pub encoding: Option<&'static Encoding>, // None means going by the BOM
+	pub help: bool,
+	// where each setting came from, for --verbose: (source, settings)
+	pub sources: Vec<(String, Vec<String>)>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+	UnknownOption(String),
+	MissingValue(String),
+	UnexpectedValue(String),
+	InvalidValue { option: String, value: String },
+	Requires { option: String, required: String },
+	Conflicts { option: String, other: String },
+	FollowsOneFile,
+	PatternFile { path: String, reason: String },
+	ConfigFile { path: String, reason: String },
+	Syntax(String),
+	NotADefault(String),
+	// something wrong in a config file or MINIGREP_OPTS, and where
+	Layer { name: String, error: Box<ConfigError> },
+	MissingPattern,
+}
+
+impl fmt::Display for ConfigError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			ConfigError::UnknownOption(option) => {
+				write!(f, "unrecognized option '{option}'")
+			}
+			ConfigError::MissingValue(option) => {
+				write!(f, "option '{option}' requires an argument")
+			}
+			ConfigError::UnexpectedValue(option) => {
+				write!(f, "option '{option}' doesn't allow an argument")
+			}
+			ConfigError::InvalidValue { option, value } => {
+				write!(f, "invalid argument '{value}' for '{option}'")
+			}
+			ConfigError::Requires { option, required } => {
+				write!(f, "option '{option}' only works together with '{required}'")
+			}
+			ConfigError::Conflicts { option, other } => {
+				write!(f, "options '{option}' and '{other}' can't be used together")
+			}
+			ConfigError::FollowsOneFile => write!(f, "option '--follow' watches a single FILE"),
+			ConfigError::PatternFile { path, reason } => {
+				write!(f, "can't read patterns from '{path}': {reason}")
+			}
+			ConfigError::ConfigFile { path, reason } => {
+				write!(f, "can't read config file '{path}': {reason}")
+			}
+			ConfigError::Syntax(text) => write!(f, "can't make sense of '{text}'"),
+			ConfigError::NotADefault(what) => {
+				write!(f, "'{what}' only works on the command line")
+			}
+			ConfigError::Layer { name, error } => write!(f, "{name}: {error}"),
+			ConfigError::MissingPattern => write!(f, "no PATTERN was given"),
+		}
+	}
+}
+
+// Display plus Debug is all Error needs, source only matters for Layer
+impl Error for ConfigError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			ConfigError::Layer { error, .. } => Some(error.as_ref()),
+			_ => None,
+		}
+	}
+}
+
+#[derive(Clone, Copy)]
+enum Opt {
+	IgnoreCase, SmartCase, Verbose, Count, FilesWithMatches, FilesWithoutMatch,
+	MaxCount, Quiet, Regexp, Fuzzy, Recursive, Follow, SearchZip, LineNumber, ByteOffset,
+	AfterContext, BeforeContext, Context, InvertMatch, WordRegexp,
+	LineRegexp, Multiline, Pattern, PatternFile, Threads, Color, Json, Replace, InPlace, Include, Exclude,
+	NoIgnore, Hidden, BinaryFiles, Text, Encoding, Help,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Takes {
+	Nothing,
+	Value,    // -A 2, -A2, --after-context 2, --after-context=2
+	Optional, // only in the --color=WHEN form, a bare --color is fine too
+			  // (--in-place=SUFFIX works the same way)
+}
+
+// every option we know: short name, long name, what it is, what it takes
+const OPTIONS: &[(Option<char>, &str, Opt, Takes)] = &[
+	(Some('i'), "ignore-case", Opt::IgnoreCase, Takes::Nothing),
+	(Some('S'), "smart-case", Opt::SmartCase, Takes::Nothing),
+	(None, "verbose", Opt::Verbose, Takes::Nothing),
+	(Some('c'), "count", Opt::Count, Takes::Nothing),
+	(Some('l'), "files-with-matches", Opt::FilesWithMatches, Takes::Nothing),
+	(Some('L'), "files-without-match", Opt::FilesWithoutMatch, Takes::Nothing),
+	(Some('m'), "max-count", Opt::MaxCount, Takes::Value),
+	(Some('q'), "quiet", Opt::Quiet, Takes::Nothing),
+	(None, "silent", Opt::Quiet, Takes::Nothing),
+	(Some('E'), "regexp", Opt::Regexp, Takes::Nothing),
+	(None, "fuzzy", Opt::Fuzzy, Takes::Value),
+	(Some('r'), "recursive", Opt::Recursive, Takes::Nothing),
+	(Some('F'), "follow", Opt::Follow, Takes::Nothing),
+	(Some('z'), "search-zip", Opt::SearchZip, Takes::Nothing),
+	(Some('n'), "line-number", Opt::LineNumber, Takes::Nothing),
+	(Some('b'), "byte-offset", Opt::ByteOffset, Takes::Nothing),
+	(Some('A'), "after-context", Opt::AfterContext, Takes::Value),
+	(Some('B'), "before-context", Opt::BeforeContext, Takes::Value),
+	(Some('C'), "context", Opt::Context, Takes::Value),
+	(Some('v'), "invert-match", Opt::InvertMatch, Takes::Nothing),
+	(Some('w'), "word-regexp", Opt::WordRegexp, Takes::Nothing),
+	(Some('x'), "line-regexp", Opt::LineRegexp, Takes::Nothing),
+	(Some('U'), "multiline", Opt::Multiline, Takes::Nothing),
+	(Some('e'), "pattern", Opt::Pattern, Takes::Value),
+	(Some('f'), "file", Opt::PatternFile, Takes::Value),
+	(Some('j'), "threads", Opt::Threads, Takes::Value),
+	(None, "color", Opt::Color, Takes::Optional),
+	(None, "colour", Opt::Color, Takes::Optional),
+	(None, "json", Opt::Json, Takes::Nothing),
+	(None, "replace", Opt::Replace, Takes::Value),
+	(None, "in-place", Opt::InPlace, Takes::Optional),
+	(None, "include", Opt::Include, Takes::Value),
+	(None, "exclude", Opt::Exclude, Takes::Value),
+	(None, "no-ignore", Opt::NoIgnore, Takes::Nothing),
+	(None, "hidden", Opt::Hidden, Takes::Nothing),
+	(None, "binary-files", Opt::BinaryFiles, Takes::Value),
+	(Some('a'), "text", Opt::Text, Takes::Nothing),
+	(None, "encoding", Opt::Encoding, Takes::Value),
+	(Some('h'), "help", Opt::Help, Takes::Nothing),
+];
+
+// settings that only make sense once every argument has been seen
+struct Parser {
+	config: Config,
+	positionals: Vec<String>,
+	// once -e or -f is used, every positional is a FILE
+	patterns_given: bool,
+	// -A and -B win over -C no matter which order they came in
+	before_context: Option<usize>,
+	after_context: Option<usize>,
+	context: Option<usize>,
+	// while reading defaults, which can't hold patterns or FILEs
+	defaults: bool,
+}
+
+impl Config {
+	pub fn new(
+		mut args: impl Iterator<Item = String>,
+	) -> Result<Config, ConfigError> {
+		/* new now uses iterators to construct the config from args.
+		 * options may come before or after PATTERN and the FILEs, short
+		 * ones can be bundled (-ic) and `--` ends the options for good.
+		 * no FILE at all means standard input will be searched.
+		 */
+		args.next(); // skip the program name
+		
+		// a missing file is only a problem when MINIGREP_CONFIG named it
+		let file = match defaults::config_path() {
+			Some(path) => match fs::read_to_string(&path) {
+				Ok(text) => Some((path.display().to_string(), text)),
+				Err(e) if e.kind() == io::ErrorKind::NotFound
+					&& env::var_os("MINIGREP_CONFIG").is_none() => None,
+				Err(e) => return Err(ConfigError::ConfigFile {
+					path: path.display().to_string(),
+					reason: e.to_string(),
+				}),
+			},
+			None => None,
+		};
+		
+		Config::layered(&environment(), file, env::var("MINIGREP_OPTS").ok(), args)
+	}
+	
+	pub fn from_args(
+		mut args: impl Iterator<Item = String>,
+	) -> Result<Config, ConfigError> {
+		/* the command line alone, like new but without the environment,
+		 * the config file or MINIGREP_OPTS. what a program using minigrep
+		 * as a library wants, so its users' settings don't leak in, and
+		 * what tests want, so the machine they run on doesn't either.
+		 */
+		args.next(); // skip the program name, as new does
+		Config::layered(&[], None, None, args)
+	}
+	
+	fn layered(
+		env: &[(String, String)],
+		file: Option<(String, String)>,
+		opts: Option<String>,
+		args: impl Iterator<Item = String>,
+	) -> Result<Config, ConfigError> {
+		/* each layer starts from what the one before it left, so the
+		 * environment variables are overridden by the config file, that by
+		 * MINIGREP_OPTS, and all of them by the command line.
+		 */
+		let mut parser = Parser {
+			config: Config::from_env(env),
+			positionals: Vec::new(),
+			patterns_given: false,
+			before_context: None,
+			after_context: None,
+			context: None,
+			defaults: true,
+		};
+		
+		if let Some((path, text)) = file {
+			parser.config.sources.push((format!("config file {path}"), Vec::new()));
+			parser.parse_file(&path, &text)?;
+		}
+		
+		if let Some(opts) = opts {
+			let name = String::from("MINIGREP_OPTS");
+			let in_opts = |error| ConfigError::Layer { name: name.clone(), error: Box::new(error) };
+			
+			parser.config.sources.push((name.clone(), Vec::new()));
+			let words = defaults::split_words(&opts)
+				.ok_or_else(|| in_opts(ConfigError::Syntax(opts.clone())))?;
+			parser.parse(words.into_iter()).map_err(in_opts)?;
+			if let Some(word) = parser.positionals.first() {
+				return Err(in_opts(ConfigError::NotADefault(word.clone())));
+			}
+		}
+		
+		parser.defaults = false;
+		parser.config.sources.push((String::from("command line"), Vec::new()));
+		parser.parse(args)?;
+		parser.finish()
+	}
+	
+	fn from_env(env: &[(String, String)]) -> Config {
+		// the value of a variable, if it's set
+		let var = |name: &str| env.iter().find(|(n, _)| n == name).map(|(_, value)| value.as_str());
+		// https://no-color.org: a non-empty NO_COLOR means no color by default
+		let no_color = var("NO_COLOR").is_some_and(|value| !value.is_empty());
+		
+		let mut set: Vec<String> = ["IGNORE_CASE", "VERBOSE", "COUNT"].iter()
+			.filter(|name| var(name).is_some())
+			.map(|name| name.to_string())
+			.collect();
+		if no_color {
+			set.push(String::from("NO_COLOR"));
+		}
+		
+		Config {
+			patterns: Vec::new(),
+			file_paths: Vec::new(),
+			ignore_case: var("IGNORE_CASE").is_some(),
+			smart_case: false,
+			verbose: var("VERBOSE").is_some(),
+			count: var("COUNT").is_some(),
+			files_with_matches: false,
+			files_without_match: false,
+			max_count: None,
+			quiet: false,
+			regexp: false,
+			fuzzy: None,
+			recursive: false,
+			follow: false,
+			search_zip: false,
+			line_number: false,
+			byte_offset: false,
+			before_context: 0,
+			after_context: 0,
+			invert_match: false,
+			word_regexp: false,
+			line_regexp: false,
+			multiline: false,
+			threads: 1,
+			color: if no_color { ColorChoice::Never } else { ColorChoice::Auto },
+			json: false,
+			replace: None,
+			in_place: false,
+			backup_suffix: None,
+			include: Vec::new(),
+			exclude: Vec::new(),
+			no_ignore: false,
+			hidden: false,
+			binary_files: BinaryFiles::Binary,
+			encoding: None,
+			help: false,
+			sources: vec![(String::from("environment"), set)],
+		}
+	}
+}
+
+fn environment() -> Vec<(String, String)> {
+	// the variables Config cares about, as they're set for this process
+	["IGNORE_CASE", "VERBOSE", "COUNT", "NO_COLOR"].iter()
+		.filter_map(|name| Some((name.to_string(), env::var(name).ok()?)))
+		.collect()
+}
+
+impl Parser {
+	fn parse(&mut self, mut args: impl Iterator<Item = String>) -> Result<(), ConfigError> {
+		while let Some(arg) = args.next() {
+			if arg == "--" {
+				// everything after this is PATTERN or FILE, even "-i"
+				self.positionals.extend(args);
+				break;
+			}
+			
+			if let Some(long) = arg.strip_prefix("--") {
+				let (name, inline) = match long.split_once('=') {
+					Some((name, value)) => (name, Some(value.to_string())),
+					None => (long, None),
+				};
+				let (opt, takes) = lookup_long(name)
+					.ok_or_else(|| ConfigError::UnknownOption(arg.clone()))?;
+				let shown = format!("--{name}");
+				
+				let value = match (takes, inline) {
+					(Takes::Nothing, Some(_)) => {
+						return Err(ConfigError::UnexpectedValue(shown));
+					}
+					(Takes::Value, None) => match args.next() {
+						Some(value) => Some(value),
+						None => return Err(ConfigError::MissingValue(shown)),
+					},
+					(_, inline) => inline,
+				};
+				
+				self.apply(opt, &shown, value)?;
+			} else if arg.len() > 1 && arg.starts_with('-') {
+				// a bundle like -inA2: flags until one of them takes a value
+				let mut rest = &arg[1..];
+				
+				while let Some(c) = rest.chars().next() {
+					rest = &rest[c.len_utf8()..];
+					let (opt, takes) = lookup_short(c)
+						.ok_or_else(|| ConfigError::UnknownOption(format!("-{c}")))?;
+					let shown = format!("-{c}");
+					
+					if takes == Takes::Nothing {
+						self.apply(opt, &shown, None)?;
+						continue;
+					}
+					
+					// the rest of the bundle is the value, or else the next arg
+					let value = if !rest.is_empty() {
+						rest.to_string()
+					} else {
+						args.next().ok_or_else(|| ConfigError::MissingValue(shown.clone()))?
+					};
+					self.apply(opt, &shown, Some(value))?;
+					break;
+				}
+			} else {
+				// "-" on its own is a FILE meaning standard input
+				self.positionals.push(arg);
+			}
+		}
+		
+		Ok(())
+	}
+	
+	fn parse_file(&mut self, path: &str, text: &str) -> Result<(), ConfigError> {
+		// errors point at the line, the way a compiler would
+		let at_line = |line: usize, error| ConfigError::Layer {
+			name: format!("{path}, line {line}"),
+			error: Box::new(error),
+		};
+		
+		let settings = defaults::parse_file(text).map_err(|line| {
+			let bad = text.lines().nth(line - 1).unwrap_or_default().trim();
+			at_line(line, ConfigError::Syntax(bad.to_string()))
+		})?;
+		
+		for (line, key, value) in settings {
+			self.setting(&key, value).map_err(|error| at_line(line, error))?;
+		}
+		
+		Ok(())
+	}
+	
+	fn setting(&mut self, key: &str, value: Value) -> Result<(), ConfigError> {
+		// a config file line is a long option, with true and false for flags
+		let shown = format!("--{key}");
+		let (opt, takes) = lookup_long(key)
+			.ok_or_else(|| ConfigError::UnknownOption(key.to_string()))?;
+		
+		match (takes, value) {
+			(Takes::Nothing | Takes::Optional, Value::Flag(true)) => self.apply(opt, &shown, None),
+			(Takes::Nothing, Value::Flag(false)) => {
+				self.unset(opt, &shown);
+				Ok(())
+			}
+			(Takes::Nothing, Value::Text(_)) => Err(ConfigError::UnexpectedValue(shown)),
+			(_, Value::Flag(_)) => Err(ConfigError::MissingValue(shown)),
+			// an array is the option given once for every item
+			(_, Value::Text(items)) => {
+				items.into_iter().try_for_each(|item| self.apply(opt, &shown, Some(item)))
+			}
+		}
+	}
+	
+	fn record(&mut self, setting: String) {
+		if let Some((_, settings)) = self.config.sources.last_mut() {
+			settings.push(setting);
+		}
+	}
+	
+	fn unset(&mut self, opt: Opt, shown: &str) {
+		// turns a flag a lower layer set back off, only config files can
+		self.record(format!("{shown}=false"));
+		let config = &mut self.config;
+		
+		match opt {
+			Opt::IgnoreCase => config.ignore_case = false,
+			Opt::SmartCase => config.smart_case = false,
+			Opt::Verbose => config.verbose = false,
+			Opt::Count => config.count = false,
+			Opt::FilesWithMatches => config.files_with_matches = false,
+			Opt::FilesWithoutMatch => config.files_without_match = false,
+			Opt::Quiet => config.quiet = false,
+			Opt::Regexp => config.regexp = false,
+			Opt::Recursive => config.recursive = false,
+			Opt::Follow => config.follow = false,
+			Opt::SearchZip => config.search_zip = false,
+			Opt::LineNumber => config.line_number = false,
+			Opt::ByteOffset => config.byte_offset = false,
+			Opt::InvertMatch => config.invert_match = false,
+			Opt::WordRegexp => config.word_regexp = false,
+			Opt::LineRegexp => config.line_regexp = false,
+			Opt::Multiline => config.multiline = false,
+			Opt::Json => config.json = false,
+			Opt::NoIgnore => config.no_ignore = false,
+			Opt::Hidden => config.hidden = false,
+			Opt::Text => config.binary_files = BinaryFiles::Binary,
+			_ => (),
+		}
+	}
+	
+	fn apply(&mut self, opt: Opt, shown: &str, value: Option<String>) -> Result<(), ConfigError> {
+		// these would turn the first FILE into a PATTERN, or never search
+		if self.defaults && matches!(opt, Opt::Pattern | Opt::PatternFile | Opt::Help) {
+			return Err(ConfigError::NotADefault(shown.to_string()));
+		}
+		self.record(match &value {
+			Some(value) => format!("{shown}={value}"),
+			None => shown.to_string(),
+		});
+		
+		let config = &mut self.config;
+		
+		match opt {
+			// whichever of -i and -S comes last gets its way
+			Opt::IgnoreCase => {
+				config.ignore_case = true;
+				config.smart_case = false;
+			}
+			Opt::SmartCase => config.smart_case = true,
+			Opt::Verbose => config.verbose = true,
+			Opt::Count => config.count = true,
+			// -l and -L can't both list, so the later one wins
+			Opt::FilesWithMatches => {
+				config.files_with_matches = true;
+				config.files_without_match = false;
+			}
+			Opt::FilesWithoutMatch => {
+				config.files_without_match = true;
+				config.files_with_matches = false;
+			}
+			Opt::MaxCount => config.max_count = Some(number(shown, value, 0)?),
+			Opt::Quiet => config.quiet = true,
+			Opt::Regexp => config.regexp = true,
+			Opt::Fuzzy => config.fuzzy = Some(number(shown, value, 0)?),
+			Opt::Recursive => config.recursive = true,
+			Opt::Follow => config.follow = true,
+			Opt::SearchZip => config.search_zip = true,
+			Opt::LineNumber => config.line_number = true,
+			Opt::ByteOffset => config.byte_offset = true,
+			Opt::AfterContext => self.after_context = Some(number(shown, value, 0)?),
+			Opt::BeforeContext => self.before_context = Some(number(shown, value, 0)?),
+			Opt::Context => self.context = Some(number(shown, value, 0)?),
+			Opt::InvertMatch => config.invert_match = true,
+			Opt::WordRegexp => config.word_regexp = true,
+			Opt::LineRegexp => config.line_regexp = true,
+			Opt::Multiline => config.multiline = true,
+			Opt::Pattern => {
+				config.patterns.extend(value);
+				self.patterns_given = true;
+			}
+			Opt::PatternFile => {
+				let path = value.unwrap_or_default();
+				config.patterns.extend(read_patterns(&path)?);
+				// an empty file is still a list of patterns, it just has none
+				self.patterns_given = true;
+			}
+			Opt::Threads => config.threads = number(shown, value, 1)?,
+			Opt::Color => {
+				config.color = match value {
+					None => ColorChoice::Auto,
+					Some(value) => ColorChoice::parse(&value).ok_or(
+						ConfigError::InvalidValue { option: shown.to_string(), value }
+					)?,
+				}
+			}
+			Opt::Json => config.json = true,
+			Opt::Replace => config.replace = value,
+			Opt::InPlace => {
+				config.in_place = true;
+				// an empty suffix would make the backup the file itself
+				config.backup_suffix = value.filter(|suffix| !suffix.is_empty());
+			}
+			// these two add up, every glob given counts
+			Opt::Include => config.include.extend(value),
+			Opt::Exclude => config.exclude.extend(value),
+			Opt::NoIgnore => config.no_ignore = true,
+			Opt::Hidden => config.hidden = true,
+			Opt::BinaryFiles => {
+				let value = value.unwrap_or_default();
+				config.binary_files = BinaryFiles::parse(&value).ok_or(
+					ConfigError::InvalidValue { option: shown.to_string(), value }
+				)?;
+			}
+			// grep's shorthand for --binary-files=text
+			Opt::Text => config.binary_files = BinaryFiles::Text,
+			Opt::Encoding => {
+				let value = value.unwrap_or_default();
+				config.encoding = match value.as_str() {
+					"auto" => None,
+					label => Some(encoding::for_label(label).ok_or(
+						ConfigError::InvalidValue { option: shown.to_string(), value: value.clone() }
+					)?),
+				};
+			}
+			Opt::Help => config.help = true,
+		}
+		
+		Ok(())
+	}
+	
+	fn finish(self) -> Result<Config, ConfigError> {
+		let mut config = self.config;
+		config.before_context = self.before_context.or(self.context).unwrap_or(0);
+		config.after_context = self.after_context.or(self.context).unwrap_or(0);
+		
+		if config.in_place && config.replace.is_none() {
+			return Err(ConfigError::Requires {
+				option: String::from("--in-place"),
+				required: String::from("--replace"),
+			});
+		}
+		// a match that spans lines has no one line to be, or to rewrite
+		if config.multiline {
+			let other = if config.line_regexp {
+				Some("--line-regexp")
+			} else if config.replace.is_some() {
+				Some("--replace")
+			} else if config.follow {
+				Some("--follow")
+			} else {
+				None
+			};
+			if let Some(other) = other {
+				return Err(ConfigError::Conflicts {
+					option: String::from("--multiline"),
+					other: String::from(other),
+				});
+			}
+		}
+		// edits are counted in characters, which means nothing to a regex
+		if config.fuzzy.is_some() && config.regexp {
+			return Err(ConfigError::Conflicts {
+				option: String::from("--fuzzy"),
+				other: String::from("--regexp"),
+			});
+		}
+		
+		let mut positionals = self.positionals.into_iter();
+		if !self.patterns_given {
+			match positionals.next() {
+				Some(query) => config.patterns.push(query),
+				// asking for help is the one time a PATTERN isn't needed
+				None if config.help => (),
+				None => return Err(ConfigError::MissingPattern),
+			}
+		}
+		// whatever is left over is the list of files
+		config.file_paths = positionals.collect();
+		
+		// following one file means never getting to the next
+		if config.follow && (config.recursive || config.file_paths.len() > 1) {
+			return Err(ConfigError::FollowsOneFile);
+		}
+		if config.follow && config.in_place {
+			return Err(ConfigError::Conflicts {
+				option: String::from("--follow"),
+				other: String::from("--in-place"),
+			});
+		}
+		// -v selects the lines with nothing in them to replace
+		if config.invert_match && config.in_place {
+			return Err(ConfigError::Conflicts {
+				option: String::from("--invert-match"),
+				other: String::from("--in-place"),
+			});
+		}
+		// the edited file would be written back as UTF-8, whatever it was before
+		if config.encoding.is_some() && config.in_place {
+			return Err(ConfigError::Conflicts {
+				option: String::from("--encoding"),
+				other: String::from("--in-place"),
+			});
+		}
+		// an archive can only be read from the start, and not written back
+		if config.search_zip && (config.follow || config.in_place) {
+			return Err(ConfigError::Conflicts {
+				option: String::from("--search-zip"),
+				other: String::from(if config.follow { "--follow" } else { "--in-place" }),
+			});
+		}
+		
+		// -S: a capital letter anywhere means the case was meant
+		if config.smart_case {
+			config.ignore_case = !config.patterns.iter()
+				.any(|pattern| has_capitals(pattern, config.regexp));
+		}
+		
+		Ok(config)
+	}
+}
+
+fn has_capitals(pattern: &str, regexp: bool) -> bool {
+	/* in a regex only the letters meant to be matched count, not the ones
+	 * in \S, \P{Greek} or (?P<Name>...). a pattern that doesn't parse can't
+	 * be searched for anyway, so then every letter counts.
+	 */
+	if regexp {
+		if let Ok(parsed) = ast::parse::Parser::new().parse(pattern) {
+			return ast::visit(&parsed, Capitals(false)).unwrap_or(true);
+		}
+	}
+	pattern.chars().any(char::is_uppercase)
+}
+
+// walks a parsed regex looking for capitals among its literal characters
+struct Capitals(bool);
+
+impl Visitor for Capitals {
+	type Output = bool;
+	type Err = ();
+	
+	fn finish(self) -> Result<bool, ()> {
+		Ok(self.0)
+	}
+	
+	fn visit_pre(&mut self, ast: &Ast) -> Result<(), ()> {
+		if let Ast::Literal(literal) = ast {
+			self.0 |= literal.c.is_uppercase();
+		}
+		Ok(())
+	}
+	
+	fn visit_class_set_item_pre(&mut self, item: &ClassSetItem) -> Result<(), ()> {
+		match item {
+			ClassSetItem::Literal(literal) => self.0 |= literal.c.is_uppercase(),
+			ClassSetItem::Range(range) => {
+				self.0 |= range.start.c.is_uppercase() || range.end.c.is_uppercase();
+			}
+			_ => (),
+		}
+		Ok(())
+	}
+}
+
+fn read_patterns(path: &str) -> Result<Vec<String>, ConfigError> {
+	// one pattern per line, and "-" reads them from standard input
+	let mut text = String::new();
+	let read = if path == "-" {
+		io::stdin().read_to_string(&mut text).map(|_| ())
+	} else {
+		fs::read_to_string(path).map(|contents| text = contents)
+	};
+	
+	match read {
+		Ok(()) => Ok(text.lines().map(String::from).collect()),
+		Err(e) => Err(ConfigError::PatternFile {
+			path: path.to_string(),
+			reason: e.to_string(),
+		}),
+	}
+}
+
+fn lookup_long(name: &str) -> Option<(Opt, Takes)> {
+	OPTIONS.iter()
+		.find(|(_, long, _, _)| *long == name)
+		.map(|&(_, _, opt, takes)| (opt, takes))
+}
+
+fn lookup_short(c: char) -> Option<(Opt, Takes)> {
+	OPTIONS.iter()
+		.find(|(short, _, _, _)| *short == Some(c))
+		.map(|&(_, _, opt, takes)| (opt, takes))
+}
+
+fn number(shown: &str, value: Option<String>, min: usize) -> Result<usize, ConfigError> {
+	// only called for options that take a value, so it's always there
+	let value = value.unwrap_or_default();
+	match value.parse() {
+		Ok(n) if n >= min => Ok(n),
+		_ => Err(ConfigError::InvalidValue { option: shown.to_string(), value }),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn parse(list: &[&str]) -> Result<Config, ConfigError> {
+		// no config file or MINIGREP_OPTS, whatever the machine has set up
+		layers(None, None, list)
+	}
+	
+	fn layers(file: Option<&str>, opts: Option<&str>, list: &[&str]) -> Result<Config, ConfigError> {
+		Config::layered(
+			&[],
+			file.map(|text| (String::from("test.conf"), text.to_string())),
+			opts.map(String::from),
+			list.iter().map(|arg| arg.to_string()),
+		)
+	}
+	
+	#[test]
+	fn bundled_short_flags() {
+		let config = parse(&["-icn", "to", "poem.txt"]).unwrap();
+		
+		assert!(config.ignore_case && config.count && config.line_number);
+		assert_eq!(vec!["to"], config.patterns);
+		assert_eq!(vec!["poem.txt"], config.file_paths);
+	}
+	
+	#[test]
+	fn values_every_which_way() {
+		let config = parse(&["-nA2", "--before-context=3", "-j", "4", "--threads", "5", "to"]).unwrap();
+		
+		assert_eq!(2, config.after_context);
+		assert_eq!(3, config.before_context);
+		assert_eq!(5, config.threads); // the last one given wins
+		assert!(config.line_number);
+		
+		let config = parse(&["--context", "1", "-C2", "to"]).unwrap();
+		assert_eq!((2, 2), (config.before_context, config.after_context));
+	}
+	
+	#[test]
+	fn options_after_positionals() {
+		let config = parse(&["to", "a.txt", "-i", "b.txt", "--count"]).unwrap();
+		
+		assert!(config.ignore_case && config.count);
+		assert_eq!(vec!["to"], config.patterns);
+		assert_eq!(vec!["a.txt", "b.txt"], config.file_paths);
+	}
+	
+	#[test]
+	fn double_dash_ends_options() {
+		let config = parse(&["-i", "--", "-v", "--count", "-"]).unwrap();
+		
+		assert!(config.ignore_case);
+		assert!(!config.invert_match && !config.count);
+		assert_eq!(vec!["-v"], config.patterns);
+		assert_eq!(vec!["--count", "-"], config.file_paths);
+	}
+	
+	#[test]
+	fn smart_case_looks_past_regex_syntax() {
+		let ignores_case = |args: &[&str]| parse(args).unwrap().ignore_case;
+		
+		// escapes, classes and group names aren't capitals being searched for
+		assert!(ignores_case(&["-S", "-E", r"\Sx\W\D\B"]));
+		assert!(ignores_case(&["-S", "-E", r"\P{Greek}(?P<Name>[a-z])"]));
+		// but they would be without -E, and a literal one still is with it
+		assert!(!ignores_case(&["-S", r"\Sx"]));
+		assert!(!ignores_case(&["-S", "-E", r"\Sx|Y"]));
+		assert!(!ignores_case(&["-S", "-E", r"[A-Z]x"]));
+		assert!(!ignores_case(&["-S", "-E", r"\x41"]));
+	}
+	
+	#[test]
+	fn replace_and_in_place() {
+		let config = parse(&["--replace", "toad", "--in-place=.bak", "frog", "poem.txt"]).unwrap();
+		assert_eq!(Some(String::from("toad")), config.replace);
+		assert!(config.in_place);
+		assert_eq!(Some(String::from(".bak")), config.backup_suffix);
+		
+		// a bare --in-place keeps no backup and doesn't eat the next argument
+		let config = parse(&["--replace=", "--in-place", "frog", "poem.txt"]).unwrap();
+		assert_eq!(Some(String::new()), config.replace);
+		assert_eq!(None, config.backup_suffix);
+		assert_eq!(vec!["frog"], config.patterns);
+		
+		assert_eq!(
+			Some(ConfigError::Conflicts {
+				option: String::from("--invert-match"),
+				other: String::from("--in-place"),
+			}),
+			parse(&["-v", "--replace=toad", "--in-place", "frog", "poem.txt"]).err()
+		);
+		// printing what -v selects is fine, there's just nothing replaced in it
+		assert!(parse(&["-v", "--replace=toad", "frog", "poem.txt"]).is_ok());
+	}
+	
+	#[test]
+	fn patterns_from_options() {
+		let list = env::temp_dir().join("minigrep_patterns_from_options.txt");
+		fs::write(&list, "toad\r\nbog\n").unwrap();
+		let list = list.to_str().unwrap();
+		
+		// with -e or -f, the first positional is a FILE rather than the PATTERN
+		let config = parse(&["-e", "frog", "poem.txt", "-f", list, "--pattern=June"]).unwrap();
+		assert_eq!(vec!["frog", "toad", "bog", "June"], config.patterns);
+		assert_eq!(vec!["poem.txt"], config.file_paths);
+		
+		assert_eq!(Vec::<String>::new(), parse(&["-f", "/dev/null"]).unwrap().patterns);
+		
+		match parse(&["-f", "/nonexistent/patterns", "poem.txt"]).err() {
+			Some(ConfigError::PatternFile { path, .. }) => assert_eq!("/nonexistent/patterns", path),
+			_ => panic!("expected a PatternFile error"),
+		}
+		
+		fs::remove_file(list).unwrap();
+	}
+	
+	#[test]
+	fn globs_add_up() {
+		let config = parse(&[
+			"-r", "--include=*.rs", "--exclude", "target", "--include", "*.txt",
+			"--no-ignore", "--hidden", "to",
+		]).unwrap();
+		
+		assert_eq!(vec!["*.rs", "*.txt"], config.include);
+		assert_eq!(vec!["target"], config.exclude);
+		assert!(config.no_ignore && config.hidden);
+	}
+	
+	#[test]
+	fn binary_policies() {
+		assert_eq!(BinaryFiles::Binary, parse(&["to"]).unwrap().binary_files);
+		assert_eq!(BinaryFiles::Text, parse(&["-a", "to"]).unwrap().binary_files);
+		assert_eq!(BinaryFiles::WithoutMatch,
+			parse(&["--binary-files=without-match", "to"]).unwrap().binary_files);
+		assert_eq!(BinaryFiles::Binary,
+			parse(&["-a", "--binary-files", "binary", "to"]).unwrap().binary_files);
+		assert!(parse(&["--binary-files=maybe", "to"]).is_err());
+	}
+	
+	#[test]
+	fn layers_override_in_order() {
+		let file = "\
+line-number = true
+context = 1
+exclude = [\"target\", \"*.lock\"]
+color = never
+";
+		let config = layers(Some(file), Some("-A 3 --color=always"), &["-B2", "to"]).unwrap();
+		
+		assert!(config.line_number);
+		// -C from the file, then -A from MINIGREP_OPTS, then -B from the command line
+		assert_eq!((2, 3), (config.before_context, config.after_context));
+		assert_eq!(vec!["target", "*.lock"], config.exclude);
+		assert_eq!(ColorChoice::Always, config.color);
+		assert_eq!(vec!["to"], config.patterns);
+	}
+	
+	#[test]
+	fn environment_comes_first() {
+		let env = [
+			(String::from("IGNORE_CASE"), String::new()),
+			(String::from("COUNT"), String::from("1")),
+		];
+		let config = Config::layered(&env, None, None, ["to"].iter().map(|arg| arg.to_string())).unwrap();
+		assert!(config.ignore_case && config.count && !config.verbose);
+		
+		// and everything after it can say otherwise
+		let file = Some((String::from("test.conf"), String::from("ignore-case = false")));
+		let config = Config::layered(&env, file, None, ["to"].iter().map(|arg| arg.to_string())).unwrap();
+		assert!(!config.ignore_case && config.count);
+		
+		// none of it comes from the real environment, whatever that holds
+		let config = Config::from_args(["minigrep", "to"].iter().map(|arg| arg.to_string())).unwrap();
+		assert!(!config.ignore_case && !config.count && !config.verbose);
+		assert_eq!(ColorChoice::Auto, config.color);
+	}
+	
+	#[test]
+	fn color_respects_no_color() {
+		let color = |value: &str, list: &[&str]| {
+			let env = [(String::from("NO_COLOR"), value.to_string())];
+			Config::layered(&env, None, None, list.iter().map(|arg| arg.to_string())).unwrap().color
+		};
+		
+		assert_eq!(ColorChoice::Never, color("1", &["o"]));
+		// asking for it on the command line still wins
+		assert_eq!(ColorChoice::Always, color("1", &["--color=always", "o"]));
+		// set but empty doesn't count
+		assert_eq!(ColorChoice::Auto, color("", &["o"]));
+	}
+	
+	#[test]
+	fn file_can_turn_flags_off() {
+		let config = layers(Some("ignore-case = true\nignore-case = false"), None, &["to"]).unwrap();
+		assert!(!config.ignore_case);
+		
+		let config = layers(Some("text = true\ntext = false"), None, &["to"]).unwrap();
+		assert_eq!(BinaryFiles::Binary, config.binary_files);
+	}
+	
+	#[test]
+	fn sources_are_recorded() {
+		let config = layers(Some("count = true\n"), Some("-n"), &["-i", "to"]).unwrap();
+		let sources: Vec<(&str, Vec<&str>)> = config.sources.iter()
+			.skip(1) // whatever the environment variables said
+			.map(|(name, settings)| (name.as_str(), settings.iter().map(String::as_str).collect()))
+			.collect();
+		
+		assert_eq!(
+			vec![
+				("config file test.conf", vec!["--count"]),
+				("MINIGREP_OPTS", vec!["-n"]),
+				("command line", vec!["-i"]),
+			],
+			sources
+		);
+	}
+	
+	#[test]
+	fn layer_errors() {
+		let at_line = |line: usize, error| ConfigError::Layer {
+			name: format!("test.conf, line {line}"),
+			error: Box::new(error),
+		};
+		
+		assert_eq!(Some(at_line(2, ConfigError::UnknownOption(String::from("colour-me")))),
+			layers(Some("count = true\ncolour-me = true"), None, &["to"]).err());
+		assert_eq!(Some(at_line(1, ConfigError::Syntax(String::from("line-number")))),
+			layers(Some("line-number"), None, &["to"]).err());
+		assert_eq!(Some(at_line(1, ConfigError::UnexpectedValue(String::from("--count")))),
+			layers(Some("count = 2"), None, &["to"]).err());
+		assert_eq!(Some(at_line(1, ConfigError::NotADefault(String::from("--pattern")))),
+			layers(Some("pattern = \"frog\""), None, &["to"]).err());
+		
+		let in_opts = |error| ConfigError::Layer {
+			name: String::from("MINIGREP_OPTS"),
+			error: Box::new(error),
+		};
+		assert_eq!(Some(in_opts(ConfigError::NotADefault(String::from("frog")))),
+			layers(None, Some("-n frog"), &["to"]).err());
+		assert_eq!(Some(in_opts(ConfigError::Syntax(String::from("-n 'open")))),
+			layers(None, Some("-n 'open"), &["to"]).err());
+		
+		let err = layers(None, Some("-e frog"), &["to"]).err().unwrap();
+		assert_eq!("MINIGREP_OPTS: '-e' only works on the command line", err.to_string());
+		assert!(err.source().is_some());
+	}
+	
+	#[test]
+	fn summarizing_modes() {
+		let config = parse(&["-l", "-m", "3", "to"]).unwrap();
+		assert!(config.files_with_matches && !config.files_without_match);
+		assert_eq!(Some(3), config.max_count);
+		
+		let config = parse(&["-lL", "--silent", "to"]).unwrap();
+		assert!(!config.files_with_matches && config.files_without_match && config.quiet);
+		
+		assert_eq!(Some(0), parse(&["--max-count=0", "to"]).unwrap().max_count);
+		assert!(parse(&["-m", "-1", "to"]).is_err());
+	}
+	
+	#[test]
+	fn fuzzy_takes_a_distance() {
+		assert_eq!(Some(2), parse(&["--fuzzy", "2", "to"]).unwrap().fuzzy);
+		assert_eq!(None, parse(&["to"]).unwrap().fuzzy);
+		assert!(parse(&["--fuzzy=two", "to"]).is_err());
+		assert_eq!(
+			Some(ConfigError::Conflicts {
+				option: String::from("--fuzzy"),
+				other: String::from("--regexp"),
+			}),
+			parse(&["-E", "--fuzzy=1", "to"]).err()
+		);
+	}
+	
+	#[test]
+	fn follow_one_file() {
+		assert!(parse(&["-F", "error", "app.log"]).unwrap().follow);
+		assert!(parse(&["--follow", "error"]).unwrap().follow);
+		assert_eq!(Some(ConfigError::FollowsOneFile), parse(&["-F", "error", "a.log", "b.log"]).err());
+		assert_eq!(Some(ConfigError::FollowsOneFile), parse(&["-rF", "error", "logs"]).err());
+	}
+	
+	#[test]
+	fn search_zip_only_reads() {
+		assert!(parse(&["-z", "error", "app.log.gz"]).unwrap().search_zip);
+		assert!(!parse(&["error"]).unwrap().search_zip);
+		assert_eq!(
+			Some(ConfigError::Conflicts {
+				option: String::from("--search-zip"),
+				other: String::from("--follow"),
+			}),
+			parse(&["-zF", "error", "app.log.gz"]).err()
+		);
+		assert!(parse(&["-z", "--replace=x", "--in-place", "a", "b.gz"]).is_err());
+	}
+	
+	#[test]
+	fn encoding_labels() {
+		assert_eq!(Some(encoding_rs::WINDOWS_1252), parse(&["--encoding=latin1", "to"]).unwrap().encoding);
+		assert_eq!(Some(encoding_rs::UTF_16BE), parse(&["--encoding", "utf-16be", "to"]).unwrap().encoding);
+		assert_eq!(None, parse(&["--encoding=auto", "to"]).unwrap().encoding);
+		assert_eq!(None, parse(&["to"]).unwrap().encoding);
+		assert_eq!(
+			Some(ConfigError::InvalidValue {
+				option: String::from("--encoding"),
+				value: String::from("klingon"),
+			}),
+			parse(&["--encoding=klingon", "to"]).err()
+		);
+		assert!(parse(&["--encoding=latin1", "--replace=x", "--in-place", "a", "b"]).is_err());
+	}
+	
+	#[test]
+	fn multiline_stands_alone() {
+		assert!(parse(&["-U", "-E", "a\\nb"]).unwrap().multiline);
+		assert!(parse(&["-Uv", "a"]).is_ok());
+		for other in ["-x", "--replace=b", "-F"] {
+			assert!(matches!(
+				parse(&["-U", other, "a"]),
+				Err(ConfigError::Conflicts { .. })
+			));
+		}
+	}
+	
+	#[test]
+	fn help_needs_no_pattern() {
+		assert!(parse(&["-h"]).unwrap().help);
+		assert!(parse(&["-ih"]).unwrap().help);
+		assert!(parse(&["to", "poem.txt", "--help"]).unwrap().help);
+	}
+	
+	#[test]
+	fn errors_name_the_token() {
+		assert_eq!(Some(ConfigError::UnknownOption(String::from("--bogus"))),
+			parse(&["--bogus", "to"]).err());
+		assert_eq!(Some(ConfigError::UnknownOption(String::from("-K"))),
+			parse(&["-iK", "to"]).err());
+		assert_eq!(Some(ConfigError::MissingValue(String::from("-A"))),
+			parse(&["to", "-A"]).err());
+		assert_eq!(Some(ConfigError::UnexpectedValue(String::from("--count"))),
+			parse(&["--count=3", "to"]).err());
+		assert_eq!(
+			Some(ConfigError::InvalidValue {
+				option: String::from("-j"),
+				value: String::from("0"),
+			}),
+			parse(&["-j0", "to"]).err()
+		);
+		assert_eq!(
+			Some(ConfigError::Requires {
+				option: String::from("--in-place"),
+				required: String::from("--replace"),
+			}),
+			parse(&["--in-place", "to", "poem.txt"]).err()
+		);
+		assert_eq!(Some(ConfigError::MissingPattern), parse(&["-i"]).err());
+	}
+	
+	#[test]
+	fn errors_display() {
+		let err = parse(&["--color=maybe", "to"]).err().unwrap();
+		assert_eq!("invalid argument 'maybe' for '--color'", err.to_string());
+		
+		// it's a real std::error::Error, so `?` can box it
+		let boxed: Box<dyn Error> = Box::new(err);
+		assert!(boxed.source().is_none());
+	}
+}
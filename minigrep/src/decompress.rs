@@ -0,0 +1,224 @@
+/*
+ * -z: searching compressed files as if they weren't. What an input was
+ * compressed with is told by its first few bytes, its magic number, and
+ * never by its name, so a rotated "app.log.1" that's really gzip is still
+ * read as gzip, and a ".gz" that isn't one is searched just as it is. gzip,
+ * and zlib's wrapper around plain deflate, always work. bzip2, xz and zstd
+ * each need the cargo feature of the same name, as each brings a library.
+ */
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+
+use flate2::bufread::{MultiGzDecoder, ZlibDecoder};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+	Gzip,
+	Zlib,
+	Bzip2,
+	Xz,
+	Zstd,
+}
+
+impl Format {
+	pub fn detect(head: &[u8]) -> Option<Format> {
+		// the bytes each format's files begin with
+		if head.starts_with(&[0x1f, 0x8b]) {
+			Some(Format::Gzip)
+		} else if is_bzip2(head) {
+			Some(Format::Bzip2)
+		} else if head.starts_with(b"\xfd7zXZ\x00") {
+			Some(Format::Xz)
+		} else if head.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+			Some(Format::Zstd)
+		} else if head.len() >= 2 && head[0] == 0x78 && matches!(head[1], 0x01 | 0x9c | 0xda) {
+			/* zlib's two header bytes could be any of a few dozen pairs, "x "
+			 * and "H." among them, so only the ones zlib itself writes count.
+			 * a text file could hardly start with any of those.
+			 */
+			Some(Format::Zlib)
+		} else {
+			None
+		}
+	}
+	
+	fn feature(self) -> &'static str {
+		// the cargo feature that brings the decoder along
+		match self {
+			Format::Gzip | Format::Zlib => "default",
+			Format::Bzip2 => "bzip2",
+			Format::Xz => "xz",
+			Format::Zstd => "zstd",
+		}
+	}
+}
+
+impl fmt::Display for Format {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str(match self {
+			Format::Gzip => "gzip",
+			Format::Zlib => "zlib",
+			Format::Bzip2 => "bzip2",
+			Format::Xz => "xz",
+			Format::Zstd => "zstd",
+		})
+	}
+}
+
+fn is_bzip2(head: &[u8]) -> bool {
+	/* "BZh" and a block size from 1 to 9 could still start a line of text,
+	 * so the first block's own magic, or the end of an empty stream's, has
+	 * to follow. both are digits of pi, in BCD.
+	 */
+	head.len() >= 10
+		&& head.starts_with(b"BZh")
+		&& (b'1'..=b'9').contains(&head[3])
+		&& (head[4..10] == [0x31, 0x41, 0x59, 0x26, 0x53, 0x59]
+			|| head[4..10] == [0x17, 0x72, 0x45, 0x38, 0x50, 0x90])
+}
+
+pub fn decompress<'r>(mut reader: Box<dyn BufRead + 'r>) -> io::Result<Box<dyn BufRead + 'r>> {
+	/* the input as it reads once decompressed, or just as it is when it
+	 * doesn't look compressed. fill_buf only peeks, so either way the
+	 * magic number is still there for the decoder, or the search, to read.
+	 */
+	let Some(format) = Format::detect(reader.fill_buf()?) else {
+		return Ok(reader);
+	};
+	
+	let decoder: Box<dyn Read + 'r> = match format {
+		// Multi: `cat a.gz b.gz > c.gz` is a valid gzip file, so is bzip2's
+		Format::Gzip => Box::new(MultiGzDecoder::new(reader)),
+		Format::Zlib => Box::new(ZlibDecoder::new(reader)),
+		#[cfg(feature = "bzip2")]
+		Format::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+		#[cfg(feature = "xz")]
+		Format::Xz => Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+		#[cfg(feature = "zstd")]
+		Format::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(reader)?),
+		// every format is covered when every feature is on
+		#[allow(unreachable_patterns)]
+		_ => {
+			return Err(io::Error::new(io::ErrorKind::Unsupported, format!(
+				"{format} compressed, and minigrep was built without the '{}' feature",
+				format.feature()
+			)));
+		}
+	};
+	
+	Ok(Box::new(BufReader::new(Decoding { format, decoder })))
+}
+
+// a decoder whose errors say what it was decoding, "corrupt deflate stream" alone doesn't
+struct Decoding<'r> {
+	format: Format,
+	decoder: Box<dyn Read + 'r>,
+}
+
+impl Read for Decoding<'_> {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		self.decoder.read(buf)
+			.map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", self.format)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Write;
+	
+	use flate2::write::{GzEncoder, ZlibEncoder};
+	use flate2::Compression;
+	
+	const POEM: &[u8] = b"I'm nobody! Who are you?\nAre you nobody, too?\n";
+	
+	fn gzip(bytes: &[u8]) -> Vec<u8> {
+		let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+		encoder.write_all(bytes).unwrap();
+		encoder.finish().unwrap()
+	}
+	
+	fn read_all(bytes: &[u8]) -> io::Result<Vec<u8>> {
+		let mut text = Vec::new();
+		decompress(Box::new(bytes))?.read_to_end(&mut text)?;
+		Ok(text)
+	}
+	
+	#[test]
+	fn detect_by_magic() {
+		assert_eq!(Some(Format::Gzip), Format::detect(&gzip(POEM)));
+		assert_eq!(Some(Format::Xz), Format::detect(b"\xfd7zXZ\x00\x00\x04"));
+		assert_eq!(Some(Format::Zstd), Format::detect(b"\x28\xb5\x2f\xfd\x24"));
+		assert_eq!(Some(Format::Bzip2), Format::detect(b"BZh91AY&SY\x00"));
+		
+		// text that only starts out looking like something
+		assert_eq!(None, Format::detect(b"BZh9 is not a block size\n"));
+		assert_eq!(None, Format::detect(b"x marks the spot\n"));
+		assert_eq!(None, Format::detect(POEM));
+		assert_eq!(None, Format::detect(b""));
+	}
+	
+	#[test]
+	fn gzip_and_zlib() {
+		assert_eq!(POEM, &read_all(&gzip(POEM)).unwrap()[..]);
+		
+		// one gzip file after another is still one gzip file
+		let mut twice = gzip(POEM);
+		twice.extend(gzip(b"Then there's a pair of us!\n"));
+		assert!(read_all(&twice).unwrap().ends_with(b"too?\nThen there's a pair of us!\n"));
+		
+		let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+		encoder.write_all(POEM).unwrap();
+		assert_eq!(POEM, &read_all(&encoder.finish().unwrap()).unwrap()[..]);
+	}
+	
+	#[test]
+	fn plain_input_is_left_alone() {
+		assert_eq!(POEM, &read_all(POEM).unwrap()[..]);
+		assert!(read_all(b"").unwrap().is_empty());
+	}
+	
+	#[test]
+	fn corrupt_input_is_an_error() {
+		let mut broken = gzip(POEM);
+		broken.truncate(broken.len() / 2);
+		let e = read_all(&broken).unwrap_err();
+		assert!(e.to_string().starts_with("gzip: "), "{e}");
+		
+		let mut garbled = gzip(POEM);
+		let middle = garbled.len() / 2;
+		garbled[middle..].fill(0xff);
+		assert!(read_all(&garbled).is_err());
+	}
+	
+	#[cfg(feature = "bzip2")]
+	#[test]
+	fn bzip2() {
+		let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+		encoder.write_all(POEM).unwrap();
+		assert_eq!(POEM, &read_all(&encoder.finish().unwrap()).unwrap()[..]);
+	}
+	
+	#[cfg(feature = "xz")]
+	#[test]
+	fn xz() {
+		let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+		encoder.write_all(POEM).unwrap();
+		assert_eq!(POEM, &read_all(&encoder.finish().unwrap()).unwrap()[..]);
+	}
+	
+	#[cfg(feature = "zstd")]
+	#[test]
+	fn zstd() {
+		assert_eq!(POEM, &read_all(&zstd::encode_all(POEM, 0).unwrap()).unwrap()[..]);
+	}
+	
+	#[cfg(not(feature = "zstd"))]
+	#[test]
+	fn missing_features_are_an_error() {
+		let e = read_all(b"\x28\xb5\x2f\xfd\x24\x00").unwrap_err();
+		assert_eq!(io::ErrorKind::Unsupported, e.kind());
+		assert!(e.to_string().contains("'zstd' feature"));
+	}
+}
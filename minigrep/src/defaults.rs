@@ -0,0 +1,247 @@
+/*
+ * Where default options come from before the command line gets its say: a
+ * config file, then the MINIGREP_OPTS environment variable. The file is a
+ * small TOML-like list of long option names and values:
+ *
+ *   # always number lines, and skip build output
+ *   line-number = true
+ *   context = 2
+ *   color = "always"
+ *   exclude = ["target", "*.lock"]
+ *
+ * This module only turns the text into words and values, what they mean is
+ * up to the option parser in config.rs.
+ */
+
+use std::env;
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq)]
+pub enum Value {
+	Flag(bool),
+	Text(Vec<String>), // a single value, or every item of an array
+}
+
+pub fn config_path() -> Option<PathBuf> {
+	/* MINIGREP_CONFIG wins, and setting it to nothing means no file at all.
+	 * otherwise it's the usual place under XDG_CONFIG_HOME or ~/.config.
+	 */
+	if let Some(path) = env::var_os("MINIGREP_CONFIG") {
+		return (!path.is_empty()).then(|| PathBuf::from(path));
+	}
+	
+	let dir = match env::var_os("XDG_CONFIG_HOME") {
+		Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+		_ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+	};
+	Some(dir.join("minigrep").join("config"))
+}
+
+pub fn parse_file(text: &str) -> Result<Vec<(usize, String, Value)>, usize> {
+	// every setting with its line number, or the number of the bad line
+	let mut settings = Vec::new();
+	
+	for (i, line) in text.lines().enumerate() {
+		let line = strip_comment(line).trim();
+		if line.is_empty() {
+			continue;
+		}
+		
+		let (key, value) = line.split_once('=').ok_or(i + 1)?;
+		let key = key.trim();
+		if key.is_empty() {
+			return Err(i + 1);
+		}
+		let value = parse_value(value.trim()).ok_or(i + 1)?;
+		settings.push((i + 1, key.to_string(), value));
+	}
+	
+	Ok(settings)
+}
+
+fn strip_comment(line: &str) -> &str {
+	// a '#' starts a comment, unless it's inside a quoted string
+	let mut quoted = false;
+	let mut escaped = false;
+	
+	for (i, c) in line.char_indices() {
+		match c {
+			_ if escaped => escaped = false,
+			'\\' if quoted => escaped = true,
+			'"' => quoted = !quoted,
+			'#' if !quoted => return &line[..i],
+			_ => (),
+		}
+	}
+	
+	line
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+	match text {
+		"true" => return Some(Value::Flag(true)),
+		"false" => return Some(Value::Flag(false)),
+		_ => (),
+	}
+	
+	if let Some(inner) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+		let mut items = Vec::new();
+		let mut rest = inner.trim_start();
+		
+		while !rest.is_empty() {
+			let (item, after) = parse_scalar(rest)?;
+			items.push(item);
+			// items are separated by commas, and a trailing one is fine
+			rest = after.trim_start();
+			if let Some(after) = rest.strip_prefix(',') {
+				rest = after.trim_start();
+			} else if !rest.is_empty() {
+				return None;
+			}
+		}
+		
+		return Some(Value::Text(items));
+	}
+	
+	match parse_scalar(text)? {
+		(item, "") => Some(Value::Text(vec![item])),
+		_ => None,
+	}
+}
+
+fn parse_scalar(text: &str) -> Option<(String, &str)> {
+	/* a "quoted string" with \" and \\ escapes, or a bare word like 2 or
+	 * always running up to the next comma. returns it and what's left.
+	 */
+	let Some(quoted) = text.strip_prefix('"') else {
+		let end = text.find(',').unwrap_or(text.len());
+		let word = text[..end].trim_end();
+		if word.is_empty() || word.contains('"') {
+			return None;
+		}
+		return Some((word.to_string(), &text[end..]));
+	};
+	
+	let mut value = String::new();
+	let mut chars = quoted.char_indices();
+	while let Some((i, c)) = chars.next() {
+		match c {
+			'"' => return Some((value, &quoted[i + 1..])),
+			'\\' => match chars.next()?.1 {
+				'n' => value.push('\n'),
+				't' => value.push('\t'),
+				escaped => value.push(escaped),
+			},
+			c => value.push(c),
+		}
+	}
+	
+	None // the closing quote never came
+}
+
+pub fn split_words(text: &str) -> Option<Vec<String>> {
+	/* MINIGREP_OPTS is split on whitespace the way a shell would, so
+	 * quotes can keep a value with spaces in it together. no variables
+	 * or globbing, though. None when a quote is left open.
+	 */
+	let mut words = Vec::new();
+	let mut word = String::new();
+	let mut in_word = false;
+	let mut quote = None;
+	let mut chars = text.chars();
+	
+	while let Some(c) = chars.next() {
+		match (quote, c) {
+			(Some(q), c) if c == q => quote = None,
+			(Some('"'), '\\') => word.extend(chars.next()),
+			(Some(_), c) => word.push(c),
+			(None, '\'' | '"') => {
+				quote = Some(c);
+				in_word = true;
+			}
+			(None, '\\') => {
+				word.extend(chars.next());
+				in_word = true;
+			}
+			(None, c) if c.is_whitespace() => {
+				if in_word {
+					words.push(std::mem::take(&mut word));
+					in_word = false;
+				}
+			}
+			(None, c) => {
+				word.push(c);
+				in_word = true;
+			}
+		}
+	}
+	
+	if quote.is_some() {
+		return None;
+	}
+	if in_word {
+		words.push(word);
+	}
+	Some(words)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn text(items: &[&str]) -> Value {
+		Value::Text(items.iter().map(|item| item.to_string()).collect())
+	}
+	
+	#[test]
+	fn file_settings() {
+		let settings = parse_file("\
+# defaults for every search
+line-number = true
+hidden=false
+
+context = 2   # a comment after a value
+color = \"always\"
+exclude = [\"target\", \"*.lock\",]
+replace = \"a # b \\\"quoted\\\"\"
+").unwrap();
+		
+		assert_eq!(
+			vec![
+				(2, String::from("line-number"), Value::Flag(true)),
+				(3, String::from("hidden"), Value::Flag(false)),
+				(5, String::from("context"), text(&["2"])),
+				(6, String::from("color"), text(&["always"])),
+				(7, String::from("exclude"), text(&["target", "*.lock"])),
+				(8, String::from("replace"), text(&["a # b \"quoted\""])),
+			],
+			settings
+		);
+	}
+	
+	#[test]
+	fn file_errors_give_the_line() {
+		assert_eq!(Err(2), parse_file("count = true\nline-number\n"));
+		assert_eq!(Err(1), parse_file("= true"));
+		assert_eq!(Err(1), parse_file("color = \"always"));
+		assert_eq!(Err(1), parse_file("exclude = [\"a\" \"b\"]"));
+		assert_eq!(Err(3), parse_file("\n\ncolor = \"always\" extra"));
+	}
+	
+	#[test]
+	fn words_like_a_shell() {
+		assert_eq!(
+			Some(vec![
+				String::from("-n"),
+				String::from("--exclude"),
+				String::from("my dir"),
+				String::from("--replace=it's"),
+				String::from("a b"),
+			]),
+			split_words("  -n --exclude 'my dir'\t--replace=\"it's\" a\\ b ")
+		);
+		assert_eq!(Some(vec![String::new()]), split_words("''"));
+		assert_eq!(Some(Vec::new()), split_words("   "));
+		assert_eq!(None, split_words("--exclude 'open"));
+	}
+}
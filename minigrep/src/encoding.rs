@@ -0,0 +1,87 @@
+/*
+ * Text that isn't UTF-8. Everything after this works on UTF-8, or at least
+ * on bytes that are mostly ASCII, so other encodings are turned into UTF-8
+ * on the way in, and what comes out is UTF-8 too. Which encoding a file is
+ * in is told by its byte order mark, when it starts with one, as files from
+ * Windows tools in UTF-16 tend to. Otherwise it's what --encoding says, and
+ * with neither the bytes are searched just as they are.
+ */
+
+use std::io::{self, BufRead, BufReader};
+
+use encoding_rs::{Encoding, REPLACEMENT};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+pub fn for_label(label: &str) -> Option<&'static Encoding> {
+	/* the labels a web page could use, so "latin1", "utf-16le", "cp1252"
+	 * and friends. REPLACEMENT is what a few encodings too dangerous to
+	 * decode get, and it turns a whole file into a single U+FFFD.
+	 */
+	Encoding::for_label(label.trim().as_bytes()).filter(|&encoding| encoding != REPLACEMENT)
+}
+
+pub fn transcode<'r>(
+	mut reader: Box<dyn BufRead + 'r>, encoding: Option<&'static Encoding>
+) -> io::Result<Box<dyn BufRead + 'r>> {
+	/* a BOM wins over whatever encoding was asked for, as it's the file
+	 * itself saying. it's left out of what's read either way, it isn't
+	 * text. UTF-8 passes straight through, invalid bytes and all.
+	 */
+	if encoding.is_none() && Encoding::for_bom(reader.fill_buf()?).is_none() {
+		// nothing to do, and binary::sniff still gets to see a whole chunk
+		return Ok(reader);
+	}
+	
+	let decoder = DecodeReaderBytesBuilder::new()
+		.encoding(encoding)
+		.bom_override(true)
+		.strip_bom(true)
+		.utf8_passthru(true)
+		.build(reader);
+	Ok(Box::new(BufReader::new(decoder)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::Read;
+	
+	use encoding_rs::{UTF_16BE, UTF_16LE, WINDOWS_1252};
+	
+	fn read(bytes: &[u8], encoding: Option<&'static Encoding>) -> Vec<u8> {
+		let mut text = Vec::new();
+		transcode(Box::new(bytes), encoding).unwrap().read_to_end(&mut text).unwrap();
+		text
+	}
+	
+	#[test]
+	fn labels() {
+		// Latin-1 is decoded as its superset, the way browsers do
+		assert_eq!(Some(WINDOWS_1252), for_label("latin1"));
+		assert_eq!(Some(WINDOWS_1252), for_label("ISO-8859-1"));
+		assert_eq!(Some(UTF_16LE), for_label("utf-16le"));
+		assert_eq!(Some(UTF_16BE), for_label("UTF-16BE"));
+		assert_eq!(None, for_label("iso-2022-kr"));
+		assert_eq!(None, for_label("klingon"));
+	}
+	
+	#[test]
+	fn byte_order_marks() {
+		assert_eq!(b"h\xc3\xa9", &read(b"\xff\xfeh\x00\xe9\x00", None)[..]);
+		assert_eq!(b"h\xc3\xa9", &read(b"\xfe\xff\x00h\x00\xe9", None)[..]);
+		assert_eq!(b"h\xc3\xa9", &read(b"\xef\xbb\xbfh\xc3\xa9", None)[..]);
+		
+		// the file knows better than the command line
+		assert_eq!(b"h\xc3\xa9", &read(b"\xff\xfeh\x00\xe9\x00", Some(WINDOWS_1252))[..]);
+	}
+	
+	#[test]
+	fn labelled_or_left_alone() {
+		assert_eq!(b"h\xc3\xa9", &read(b"h\xe9", Some(WINDOWS_1252))[..]);
+		assert_eq!(b"h\xc3\xa9", &read(b"h\x00\xe9\x00", Some(UTF_16LE))[..]);
+		
+		// no BOM, no encoding: not even invalid UTF-8 is touched
+		assert_eq!(b"h\xe9\x00", &read(b"h\xe9\x00", None)[..]);
+		assert!(read(b"", None).is_empty());
+	}
+}
@@ -0,0 +1,170 @@
+/*
+ * Boyer-Moore-Horspool substring search. The query is turned into a table
+ * once, saying how far the window may jump when its last byte is a given
+ * value, so most of the haystack is skipped over without being compared.
+ * Case folding happens a byte at a time while comparing, which is exact for
+ * ASCII and needs no lowercase copy of anything. Unicode folding is only
+ * brought in for the lines where ASCII folding could get it wrong.
+ *
+ * It's what the library's search and search_case_insensitive use. The
+ * command line goes through PatternMatcher instead, whose regex and
+ * Aho-Corasick engines already skip ahead like this, so minigrep itself
+ * doesn't use a Finder.
+ */
+
+use crate::fold;
+
+pub struct Finder {
+	needle: Vec<u8>, // already case folded when ignoring case
+	shift: [usize; 256],
+	ignore_case: bool,
+	// the folded query, for lines that need real Unicode folding
+	fallback: Option<String>,
+}
+
+impl Finder {
+	pub fn new(query: &str, ignore_case: bool) -> Finder {
+		// folding can turn a query that isn't ASCII into one that is, like ß
+		let folded = if ignore_case { fold::fold(query) } else { query.to_string() };
+		let needle = folded.as_bytes().to_vec();
+		
+		// a byte that isn't in the needle lets the window jump its full length
+		let last = needle.len().saturating_sub(1);
+		let mut shift = [needle.len(); 256];
+		for (i, &b) in needle[..last].iter().enumerate() {
+			shift[b as usize] = last - i;
+			if ignore_case {
+				shift[b.to_ascii_uppercase() as usize] = last - i;
+			}
+		}
+		
+		/* folding text that isn't ASCII only gives back ASCII for a handful
+		 * of characters, like ß or the Kelvin sign. so an ASCII query none of
+		 * those can reach can't be matched by anything ASCII folding misses,
+		 * and needs no fallback at all.
+		 */
+		let fallback = ignore_case
+			&& (!folded.is_ascii() || needle.iter().any(|&b| fold::reaches_ascii(b)));
+		
+		Finder {
+			needle,
+			shift,
+			ignore_case,
+			fallback: fallback.then_some(folded),
+		}
+	}
+	
+	fn same(&self, a: u8, b: u8) -> bool {
+		// b is always from the needle, which was folded up front
+		if self.ignore_case { a.to_ascii_lowercase() == b } else { a == b }
+	}
+	
+	pub fn find(&self, haystack: &[u8]) -> Option<usize> {
+		// where the needle first starts in haystack, comparing bytes
+		let len = self.needle.len();
+		if len == 0 {
+			return Some(0);
+		}
+		
+		let mut pos = 0;
+		while pos + len <= haystack.len() {
+			let window = &haystack[pos..pos + len];
+			let last = window[len - 1];
+			
+			// check the last byte first, it's the one the shift is keyed on
+			if self.same(last, self.needle[len - 1])
+				&& window.iter().zip(&self.needle).all(|(&a, &b)| self.same(a, b))
+			{
+				return Some(pos);
+			}
+			
+			pos += self.shift[last as usize];
+		}
+		
+		None
+	}
+	
+	pub fn is_match(&self, line: &str) -> bool {
+		match &self.fallback {
+			// the slow, allocating way, but only for lines that need it
+			Some(folded) if !line.is_ascii() => fold::fold(line).contains(folded.as_str()),
+			_ => self.find(line.as_bytes()).is_some(),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn finds_the_first_occurrence() {
+		let finder = Finder::new("frog", false);
+		
+		assert_eq!(Some(19), finder.find(b"How public, like a frog"));
+		assert_eq!(Some(0), finder.find(b"frogfrog"));
+		assert_eq!(None, finder.find(b"fro"));
+		assert_eq!(None, finder.find(b"Frog"));
+		assert_eq!(Some(0), Finder::new("", false).find(b""));
+	}
+	
+	#[test]
+	fn agrees_with_contains() {
+		// every short string over a small alphabet, against a few needles
+		let alphabet = ['a', 'b', 'A', '\u{e9}'];
+		let mut haystacks = vec![String::new()];
+		for _ in 0..5 {
+			let longer: Vec<String> = haystacks.iter()
+				.flat_map(|h| alphabet.iter().map(move |c| format!("{h}{c}")))
+				.collect();
+			haystacks.extend(longer);
+		}
+		
+		for needle in ["a", "ab", "aab", "aba", "bab", "\u{e9}a", "Ab"] {
+			let finder = Finder::new(needle, false);
+			let folded = Finder::new(needle, true);
+			
+			for haystack in &haystacks {
+				assert_eq!(haystack.contains(needle), finder.is_match(haystack), "{needle} in {haystack}");
+				assert_eq!(
+					fold::fold(haystack).contains(&fold::fold(needle)),
+					folded.is_match(haystack),
+					"{needle} in {haystack}, ignoring case"
+				);
+			}
+		}
+	}
+	
+	#[test]
+	fn ascii_folding_needs_no_fallback() {
+		let finder = Finder::new("CoDe", true);
+		
+		assert!(finder.fallback.is_none());
+		assert!(finder.is_match("Decoded"));
+		assert!(finder.is_match("caf\u{e9} code"));
+		assert!(!finder.is_match("c\u{f6}de"));
+	}
+	
+	#[test]
+	fn unicode_falls_back() {
+		// the Kelvin sign lowercases to a plain k
+		let finder = Finder::new("KM", true);
+		assert!(finder.fallback.is_some());
+		assert!(finder.is_match("42 \u{212a}m"));
+		
+		let finder = Finder::new("\u{c9}T\u{c9}", true);
+		assert!(finder.is_match("l'\u{e9}t\u{e9}"));
+		assert!(!finder.is_match("ete"));
+		
+		// without -i there is nothing to fold
+		assert!(!Finder::new("km", false).is_match("42 \u{212a}m"));
+		
+		// full folding, where lowercasing falls short
+		let finder = Finder::new("STRASSE", true);
+		assert!(finder.is_match("Stra\u{df}e"));
+		// it folds to plain ASCII, so ASCII lines take the quick way
+		let finder = Finder::new("stra\u{df}e", true);
+		assert_eq!(b"strasse", &finder.needle[..]);
+		assert!(finder.is_match("STRASSE"));
+	}
+}
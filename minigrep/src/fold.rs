@@ -0,0 +1,249 @@
+/*
+ * Unicode case folding, the way CaseFolding.txt defines it for the default
+ * (non-Turkic) full folding. For nearly every character that's the same as
+ * its lowercase form, so to_lowercase does the work and the table below
+ * only lists where the two disagree: characters that fold to more than one
+ * (ß to "ss", the ligatures, Greek with an iota subscript), lowercase
+ * variants that fold onto another letter (ſ to s, final ς to σ), and a few
+ * that to_lowercase leaves alone. Sorted, so it can be binary searched.
+ */
+
+const SPECIAL: &[(char, &str)] = &[
+	('\u{b5}', "\u{3bc}"),
+	('\u{df}', "ss"),
+	('\u{130}', "i\u{307}"),
+	('\u{149}', "\u{2bc}n"),
+	('\u{17f}', "s"),
+	('\u{1f0}', "j\u{30c}"),
+	('\u{345}', "\u{3b9}"),
+	('\u{390}', "\u{3b9}\u{308}\u{301}"),
+	('\u{3b0}', "\u{3c5}\u{308}\u{301}"),
+	('\u{3c2}', "\u{3c3}"),
+	('\u{3d0}', "\u{3b2}"),
+	('\u{3d1}', "\u{3b8}"),
+	('\u{3d5}', "\u{3c6}"),
+	('\u{3d6}', "\u{3c0}"),
+	('\u{3f0}', "\u{3ba}"),
+	('\u{3f1}', "\u{3c1}"),
+	('\u{3f5}', "\u{3b5}"),
+	('\u{587}', "\u{565}\u{582}"),
+	('\u{1c80}', "\u{432}"),
+	('\u{1c81}', "\u{434}"),
+	('\u{1c82}', "\u{43e}"),
+	('\u{1c83}', "\u{441}"),
+	('\u{1c84}', "\u{442}"),
+	('\u{1c85}', "\u{442}"),
+	('\u{1c86}', "\u{44a}"),
+	('\u{1c87}', "\u{463}"),
+	('\u{1c88}', "\u{a64b}"),
+	('\u{1e96}', "h\u{331}"),
+	('\u{1e97}', "t\u{308}"),
+	('\u{1e98}', "w\u{30a}"),
+	('\u{1e99}', "y\u{30a}"),
+	('\u{1e9a}', "a\u{2be}"),
+	('\u{1e9b}', "\u{1e61}"),
+	('\u{1e9e}', "ss"),
+	('\u{1f50}', "\u{3c5}\u{313}"),
+	('\u{1f52}', "\u{3c5}\u{313}\u{300}"),
+	('\u{1f54}', "\u{3c5}\u{313}\u{301}"),
+	('\u{1f56}', "\u{3c5}\u{313}\u{342}"),
+	('\u{1fb2}', "\u{1f70}\u{3b9}"),
+	('\u{1fb3}', "\u{3b1}\u{3b9}"),
+	('\u{1fb4}', "\u{3ac}\u{3b9}"),
+	('\u{1fb6}', "\u{3b1}\u{342}"),
+	('\u{1fb7}', "\u{3b1}\u{342}\u{3b9}"),
+	('\u{1fbc}', "\u{3b1}\u{3b9}"),
+	('\u{1fbe}', "\u{3b9}"),
+	('\u{1fc2}', "\u{1f74}\u{3b9}"),
+	('\u{1fc3}', "\u{3b7}\u{3b9}"),
+	('\u{1fc4}', "\u{3ae}\u{3b9}"),
+	('\u{1fc6}', "\u{3b7}\u{342}"),
+	('\u{1fc7}', "\u{3b7}\u{342}\u{3b9}"),
+	('\u{1fcc}', "\u{3b7}\u{3b9}"),
+	('\u{1fd2}', "\u{3b9}\u{308}\u{300}"),
+	('\u{1fd3}', "\u{3b9}\u{308}\u{301}"),
+	('\u{1fd6}', "\u{3b9}\u{342}"),
+	('\u{1fd7}', "\u{3b9}\u{308}\u{342}"),
+	('\u{1fe2}', "\u{3c5}\u{308}\u{300}"),
+	('\u{1fe3}', "\u{3c5}\u{308}\u{301}"),
+	('\u{1fe4}', "\u{3c1}\u{313}"),
+	('\u{1fe6}', "\u{3c5}\u{342}"),
+	('\u{1fe7}', "\u{3c5}\u{308}\u{342}"),
+	('\u{1ff2}', "\u{1f7c}\u{3b9}"),
+	('\u{1ff3}', "\u{3c9}\u{3b9}"),
+	('\u{1ff4}', "\u{3ce}\u{3b9}"),
+	('\u{1ff6}', "\u{3c9}\u{342}"),
+	('\u{1ff7}', "\u{3c9}\u{342}\u{3b9}"),
+	('\u{1ffc}', "\u{3c9}\u{3b9}"),
+	('\u{212a}', "k"), // the Kelvin sign, lowercase does this one too
+	('\u{fb00}', "ff"),
+	('\u{fb01}', "fi"),
+	('\u{fb02}', "fl"),
+	('\u{fb03}', "ffi"),
+	('\u{fb04}', "ffl"),
+	('\u{fb05}', "st"),
+	('\u{fb06}', "st"),
+	('\u{fb13}', "\u{574}\u{576}"),
+	('\u{fb14}', "\u{574}\u{565}"),
+	('\u{fb15}', "\u{574}\u{56b}"),
+	('\u{fb16}', "\u{57e}\u{576}"),
+	('\u{fb17}', "\u{574}\u{56d}"),
+];
+
+pub fn fold_char(c: char, out: &mut String) {
+	if c.is_ascii() {
+		out.push(c.to_ascii_lowercase());
+		return;
+	}
+	if let Ok(i) = SPECIAL.binary_search_by_key(&c, |&(from, _)| from) {
+		out.push_str(SPECIAL[i].1);
+		return;
+	}
+	
+	let code = c as u32;
+	match code {
+		// Greek with a subscript iota: the plain letter, then the iota
+		0x1f80..=0x1faf => {
+			let bases = [0x1f00, 0x1f20, 0x1f60];
+			let base = bases[(code - 0x1f80) as usize / 16] + (code % 8);
+			out.extend(char::from_u32(base));
+			out.push('\u{3b9}');
+		}
+		// Cherokee is the one script that folds to its capitals
+		0x13a0..=0x13f5 => out.push(c),
+		0x13f8..=0x13fd => out.extend(char::from_u32(code - 8)),
+		0xab70..=0xabbf => out.extend(char::from_u32(code - 0xab70 + 0x13a0)),
+		_ => out.extend(c.to_lowercase()),
+	}
+}
+
+pub fn fold(text: &str) -> String {
+	let mut folded = String::with_capacity(text.len());
+	for c in text.chars() {
+		fold_char(c, &mut folded);
+	}
+	folded
+}
+
+pub fn reaches_ascii(b: u8) -> bool {
+	/* whether some character outside ASCII folds to something holding the
+	 * ASCII byte b, like the 's' in what ß folds to. when none do, a search
+	 * for b can ignore everything that isn't ASCII.
+	 */
+	SPECIAL.iter().any(|(_, to)| to.as_bytes().contains(&b))
+}
+
+// a line of bytes after folding, remembering where each byte came from
+pub struct FoldedLine {
+	pub bytes: Vec<u8>,
+	starts: Vec<usize>, // the start of the source character, per folded byte
+	ends: Vec<usize>,   // and its end
+}
+
+impl FoldedLine {
+	pub fn new(line: &[u8]) -> FoldedLine {
+		let mut folded = FoldedLine {
+			bytes: Vec::with_capacity(line.len()),
+			starts: Vec::with_capacity(line.len() + 1),
+			ends: Vec::with_capacity(line.len()),
+		};
+		let mut offset = 0;
+		let mut buf = String::new();
+		
+		for chunk in line.utf8_chunks() {
+			for (i, c) in chunk.valid().char_indices() {
+				buf.clear();
+				fold_char(c, &mut buf);
+				let (start, end) = (offset + i, offset + i + c.len_utf8());
+				folded.bytes.extend_from_slice(buf.as_bytes());
+				folded.starts.extend(std::iter::repeat_n(start, buf.len()));
+				folded.ends.extend(std::iter::repeat_n(end, buf.len()));
+			}
+			offset += chunk.valid().len();
+			
+			// bytes that aren't UTF-8 have no case, they stay as they are
+			for &b in chunk.invalid() {
+				folded.bytes.push(b);
+				folded.starts.push(offset);
+				folded.ends.push(offset + 1);
+				offset += 1;
+			}
+		}
+		
+		folded.starts.push(line.len()); // so the end of the line maps too
+		folded
+	}
+	
+	pub fn to_folded(&self, start: usize) -> usize {
+		// the first folded byte that comes from at or after start
+		self.starts.partition_point(|&from| from < start)
+	}
+	
+	pub fn to_original(&self, start: usize, end: usize) -> (usize, usize) {
+		// a match that ends partway into what ß became still takes all of ß
+		if end > start {
+			(self.starts[start], self.ends[end - 1])
+		} else {
+			(self.starts[start], self.starts[start])
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn table_is_sorted() {
+		assert!(SPECIAL.windows(2).all(|pair| pair[0].0 < pair[1].0));
+	}
+	
+	#[test]
+	fn full_folding() {
+		assert_eq!("strasse", fold("Stra\u{df}e"));
+		assert_eq!("strasse", fold("STRASSE"));
+		assert_eq!("strasse", fold("STRA\u{1e9e}E"));
+		assert_eq!("office", fold("O\u{fb03}ce"));
+		// the final sigma folds like any other
+		assert_eq!(fold("\u{3a3}\u{399}\u{3a3}\u{3a5}\u{3a6}\u{39f}\u{3a3}"),
+			fold("\u{3c3}\u{3b9}\u{3c3}\u{3c5}\u{3c6}\u{3bf}\u{3c2}"));
+		assert_eq!("\u{1f00}\u{3b9}", fold("\u{1f88}"));
+		assert_eq!("\u{13a0}", fold("\u{ab70}"));
+	}
+	
+	#[test]
+	fn turkish_i_without_tailoring() {
+		// dotted capital I keeps its dot as a combining mark
+		assert_eq!("i\u{307}stanbul", fold("\u{130}stanbul"));
+		// and the dotless i is a letter of its own, not a form of I
+		assert_eq!("\u{131}", fold("\u{131}"));
+		assert_eq!("i", fold("I"));
+	}
+	
+	#[test]
+	fn reaches_ascii_letters() {
+		assert!(reaches_ascii(b's'));
+		assert!(reaches_ascii(b'k'));
+		assert!(!reaches_ascii(b'r'));
+		assert!(!reaches_ascii(b' '));
+	}
+	
+	#[test]
+	fn folded_line_maps_back() {
+		let line = "Gro\u{df}e \u{ff} STRASSE".as_bytes();
+		let folded = FoldedLine::new(line);
+		assert_eq!("grosse \u{ff} strasse".as_bytes(), &folded.bytes[..]);
+		
+		// "ss" in the folded line is the two bytes of ß in the original
+		assert_eq!((3, 5), folded.to_original(3, 5));
+		// half of it is still all of it
+		assert_eq!((3, 5), folded.to_original(4, 5));
+		assert_eq!(6, folded.to_folded(6));
+		assert_eq!(line.len(), folded.to_original(folded.bytes.len(), folded.bytes.len()).0);
+		
+		// bytes that aren't UTF-8 go straight through
+		let folded = FoldedLine::new(b"A\xffB");
+		assert_eq!(b"a\xffb", &folded.bytes[..]);
+		assert_eq!((1, 2), folded.to_original(1, 2));
+	}
+}
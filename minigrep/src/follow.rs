@@ -0,0 +1,183 @@
+/*
+ * --follow: a reader over a file that doesn't end where the file does.
+ * Once everything there is has been read it waits, checks again every so
+ * often, and hands over whatever was appended meanwhile. Since it only ever
+ * looks like a slow file, search_reader needs no changes at all to keep
+ * going: a line being written is simply waited on until its '\n' arrives.
+ * It only reports the end once it's been asked to stop, by Ctrl-C.
+ */
+
+use std::fs::{self, File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+// how long to wait before looking at the file again
+pub const POLL: Duration = Duration::from_millis(200);
+
+// set from the Ctrl-C handler, so following stops and the search can finish
+pub static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+pub struct Tail {
+	path: PathBuf,
+	file: File,
+	position: u64, // how much of this file we've read
+	poll: Duration,
+	stop: &'static AtomicBool,
+}
+
+impl Tail {
+	pub fn open(path: PathBuf, poll: Duration, stop: &'static AtomicBool) -> io::Result<Tail> {
+		let file = File::open(&path)?;
+		Ok(Tail { path, file, position: 0, poll, stop })
+	}
+	
+	fn moved_on(&mut self) -> io::Result<bool> {
+		/* whether the file changed under us in a way that means reading on
+		 * from where we are is wrong. it can be truncated, so it's shorter
+		 * than what we've read, or rotated, meaning the path now names a new
+		 * file and the one we have open was moved away or deleted.
+		 */
+		let Ok(now) = fs::metadata(&self.path) else {
+			// rotated away and the new one isn't there yet, keep waiting
+			return Ok(false);
+		};
+		
+		if !same_file(&now, &self.file.metadata()?) {
+			// we just read the old file to its end, nothing there is lost
+			if let Ok(file) = File::open(&self.path) {
+				self.file = file;
+				self.position = 0;
+				return Ok(true);
+			}
+		} else if now.len() < self.position {
+			self.file.seek(SeekFrom::Start(0))?;
+			self.position = 0;
+			return Ok(true);
+		}
+		
+		Ok(false)
+	}
+}
+
+impl Read for Tail {
+	fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+		loop {
+			let read = self.file.read(buf)?;
+			if read > 0 {
+				self.position += read as u64;
+				return Ok(read);
+			}
+			
+			// the end of the file is only the end once we're told to stop
+			if self.stop.load(Ordering::Relaxed) {
+				return Ok(0);
+			}
+			if !self.moved_on()? {
+				thread::sleep(self.poll);
+			}
+		}
+	}
+}
+
+#[cfg(unix)]
+fn same_file(a: &Metadata, b: &Metadata) -> bool {
+	use std::os::unix::fs::MetadataExt;
+	(a.dev(), a.ino()) == (b.dev(), b.ino())
+}
+
+#[cfg(not(unix))]
+fn same_file(_: &Metadata, _: &Metadata) -> bool {
+	// no inode to go by, so only truncation can be noticed
+	true
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::io::{BufRead, BufReader, Write};
+	use std::sync::mpsc;
+	
+	use crate::search_reader;
+	use crate::stream::Event;
+	
+	// a scratch file of our own, so tests, and test runs, can go side by side
+	fn scratch(name: &str, contents: &str) -> PathBuf {
+		let file_name = format!("minigrep_follow_{name}_{}", std::process::id());
+		let path = std::env::temp_dir().join(file_name);
+		fs::write(&path, contents).unwrap();
+		path
+	}
+	
+	fn append(path: &PathBuf, text: &str) {
+		let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+		file.write_all(text.as_bytes()).unwrap();
+	}
+	
+	#[test]
+	fn follows_appends_truncation_and_rotation() {
+		// every test needs a flag of its own, one that outlives the reader
+		static STOP: AtomicBool = AtomicBool::new(false);
+		let path = scratch("grow", "one error\ntwo\n");
+		let tail = Tail::open(path.clone(), Duration::from_millis(5), &STOP).unwrap();
+		let (found_tx, found_rx) = mpsc::channel();
+		
+		let searcher = thread::spawn(move || {
+			search_reader(
+				BufReader::new(tail),
+				|line| line.windows(5).any(|w| w == b"error"),
+				0, 0, None,
+				|event| {
+					if let Event::Match(line) = event {
+						let text = String::from_utf8_lossy(line.bytes).into_owned();
+						found_tx.send((line.line_number, text)).unwrap();
+					}
+					Ok(())
+				},
+			)
+		});
+		let next = || found_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+		
+		assert_eq!((1, String::from("one error")), next());
+		
+		// half a line is held back until the rest of it shows up
+		append(&path, "three err");
+		thread::sleep(Duration::from_millis(30));
+		append(&path, "or\nfour\n");
+		assert_eq!((3, String::from("three error")), next());
+		
+		// cut short and written again, so reading starts over
+		fs::write(&path, "").unwrap();
+		thread::sleep(Duration::from_millis(30));
+		append(&path, "five error\n");
+		assert_eq!((5, String::from("five error")), next());
+		
+		// moved aside for a new file in its place, like logrotate does
+		let rotated = path.with_extension("1");
+		fs::rename(&path, &rotated).unwrap();
+		fs::write(&path, "six error\n").unwrap();
+		assert_eq!((6, String::from("six error")), next());
+		
+		STOP.store(true, Ordering::Relaxed);
+		assert_eq!(4, searcher.join().unwrap().unwrap());
+		
+		fs::remove_file(&path).unwrap();
+		fs::remove_file(&rotated).unwrap();
+	}
+	
+	#[test]
+	fn stopping_ends_the_input() {
+		static STOP: AtomicBool = AtomicBool::new(true);
+		let path = scratch("stop", "last line without a newline");
+		let mut tail = BufReader::new(Tail::open(path.clone(), POLL, &STOP).unwrap());
+		
+		let mut line = String::new();
+		tail.read_line(&mut line).unwrap();
+		assert_eq!("last line without a newline", line);
+		assert_eq!(0, tail.read_line(&mut line).unwrap());
+		
+		fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,385 @@
+/*
+ * Approximate matching: find the part of a line that is fewest edits away
+ * from the query, where an edit is inserting, deleting or swapping a single
+ * character (the Levenshtein distance). Filling in the full table of
+ * distances takes query length times line length steps, so instead each
+ * column of it is kept as bit vectors of +1/-1 differences between
+ * neighbouring cells, one bit per query character, and a whole column is
+ * worked out with a few word operations. This is Myers' bit-parallel
+ * algorithm, in the form Hyyrö gave it for queries longer than one word.
+ */
+
+use std::collections::HashMap;
+
+use crate::fold;
+
+// for each character, a bit set wherever it appears in the query
+struct Peq {
+	blocks: usize,
+	ascii: Vec<u64>, // 128 rows of `blocks` words each
+	other: HashMap<char, Vec<u64>>,
+	none: Vec<u64>,  // the row for any character not in the query
+}
+
+impl Peq {
+	fn new(query: &[char]) -> Peq {
+		let blocks = query.len().div_ceil(64);
+		let mut peq = Peq {
+			blocks,
+			ascii: vec![0; 128 * blocks],
+			other: HashMap::new(),
+			none: vec![0; blocks],
+		};
+		
+		for (i, &c) in query.iter().enumerate() {
+			let bit = 1 << (i % 64);
+			if c.is_ascii() {
+				peq.ascii[c as usize * blocks + i / 64] |= bit;
+			} else {
+				peq.other.entry(c).or_insert_with(|| vec![0; blocks])[i / 64] |= bit;
+			}
+		}
+		
+		peq
+	}
+	
+	fn get(&self, c: char) -> &[u64] {
+		if c.is_ascii() {
+			let row = c as usize * self.blocks;
+			&self.ascii[row..row + self.blocks]
+		} else {
+			self.other.get(&c).unwrap_or(&self.none)
+		}
+	}
+}
+
+// one character of the line, and the bytes of the line it came from
+#[derive(Clone, Copy)]
+struct Token {
+	c: char,
+	start: usize,
+	end: usize,
+}
+
+pub struct Fuzzy {
+	len: usize, // of the query, in characters
+	forward: Peq,
+	backward: Peq, // the query reversed, for finding where a match starts
+	max: usize,
+	ignore_case: bool,
+	whole_line: bool,
+}
+
+impl Fuzzy {
+	pub fn new(query: &str, max: usize, ignore_case: bool, whole_line: bool) -> Fuzzy {
+		let query: Vec<char> = if ignore_case {
+			fold::fold(query).chars().collect()
+		} else {
+			query.chars().collect()
+		};
+		let reversed: Vec<char> = query.iter().rev().copied().collect();
+		
+		Fuzzy {
+			len: query.len(),
+			forward: Peq::new(&query),
+			backward: Peq::new(&reversed),
+			max,
+			ignore_case,
+			whole_line,
+		}
+	}
+	
+	fn tokens(&self, line: &[u8], from: usize) -> Vec<Token> {
+		/* the characters of line[from..], folded if we're ignoring case.
+		 * a character that folds to several, like ß to "ss", gives a token
+		 * for each, all pointing back at the same bytes. bytes that aren't
+		 * UTF-8 can only ever be edited away, so they become U+FFFD.
+		 */
+		let mut tokens = Vec::with_capacity(line.len() - from);
+		let mut offset = from;
+		let mut buf = String::new();
+		
+		for chunk in line[from..].utf8_chunks() {
+			for (i, c) in chunk.valid().char_indices() {
+				let (start, end) = (offset + i, offset + i + c.len_utf8());
+				if self.ignore_case {
+					buf.clear();
+					fold::fold_char(c, &mut buf);
+					tokens.extend(buf.chars().map(|c| Token { c, start, end }));
+				} else {
+					tokens.push(Token { c, start, end });
+				}
+			}
+			offset += chunk.valid().len();
+			
+			for _ in chunk.invalid() {
+				tokens.push(Token { c: char::REPLACEMENT_CHARACTER, start: offset, end: offset + 1 });
+				offset += 1;
+			}
+		}
+		
+		tokens
+	}
+	
+	fn scan(
+		&self,
+		peq: &Peq,
+		text: impl Iterator<Item = char>,
+		anchored: bool,
+		mut column: impl FnMut(usize) -> bool,
+	) {
+		/* feeds column() the distance after each character of text, for as
+		 * long as it returns true. unanchored, that's the fewest edits
+		 * between the query and any stretch of text ending there. anchored,
+		 * the stretch has to begin where the text does.
+		 *
+		 * pv and mv hold, for each row of the column, whether the cell is
+		 * one more or one less than the one above. the score is the bottom
+		 * cell, kept up to date from what the last row did on each step.
+		 */
+		if self.len == 0 {
+			// nothing to find is found right away, or else is all insertions
+			for (i, _) in text.enumerate() {
+				if !column(if anchored { i + 1 } else { 0 }) {
+					return;
+				}
+			}
+			return;
+		}
+		
+		let last = 1 << ((self.len - 1) % 64);
+		let mut pv = vec![u64::MAX; peq.blocks];
+		let mut mv = vec![0; peq.blocks];
+		let mut score = self.len;
+		
+		for c in text {
+			let eq = peq.get(c);
+			// the top row: free to start anywhere, or one more per character
+			let mut carry: i8 = if anchored { 1 } else { 0 };
+			
+			for b in 0..peq.blocks {
+				let (p, m) = (pv[b], mv[b]);
+				let mut e = eq[b];
+				let xv = e | m;
+				if carry < 0 {
+					e |= 1;
+				}
+				let xh = ((e & p).wrapping_add(p) ^ p) | e;
+				let mut ph = m | !(xh | p);
+				let mut mh = p & xh;
+				
+				// what changed along the bottom of this block goes to the next
+				let high = if b + 1 == peq.blocks { last } else { 1 << 63 };
+				let out = if ph & high != 0 { 1 } else if mh & high != 0 { -1 } else { 0 };
+				
+				ph <<= 1;
+				mh <<= 1;
+				if carry < 0 {
+					mh |= 1;
+				} else if carry > 0 {
+					ph |= 1;
+				}
+				pv[b] = mh | !(xv | ph);
+				mv[b] = ph & xv;
+				carry = out;
+			}
+			
+			score = score.wrapping_add_signed(carry as isize);
+			if !column(score) {
+				return;
+			}
+		}
+	}
+	
+	pub fn distance(&self, line: &[u8]) -> Option<usize> {
+		// the fewest edits anywhere in line, if that's few enough
+		let tokens = self.tokens(line, 0);
+		let text = tokens.iter().map(|token| token.c);
+		let mut best = self.len;
+		
+		if self.whole_line {
+			// the score after the last character is the whole line's
+			self.scan(&self.forward, text, true, |score| {
+				best = score;
+				true
+			});
+		} else {
+			self.scan(&self.forward, text, false, |score| {
+				best = best.min(score);
+				best > 0
+			});
+		}
+		
+		(best <= self.max).then_some(best)
+	}
+	
+	pub fn find_at(&self, line: &[u8], start: usize) -> Option<(usize, usize)> {
+		/* the first match at or after start as (start, end) byte offsets.
+		 * it ends where the distance first drops to max or below, carried on
+		 * for as long as the distance doesn't rise, so "hello" takes all of
+		 * "hello" rather than stopping at "hell". then the query is run
+		 * backwards from there to find the shortest stretch that does as well.
+		 */
+		if self.whole_line {
+			return (start == 0 && self.distance(line).is_some()).then_some((0, line.len()));
+		}
+		if self.len == 0 {
+			return Some((start, start));
+		}
+		
+		let tokens = self.tokens(line, start);
+		let mut found: Option<(usize, usize)> = None;
+		let mut column = 0;
+		
+		self.scan(&self.forward, tokens.iter().map(|token| token.c), false, |score| {
+			column += 1;
+			match found {
+				Some((_, best)) if score <= best => {
+					found = Some((column, score));
+					true
+				}
+				Some(_) => false,
+				None if score <= self.max => {
+					found = Some((column, score));
+					true
+				}
+				None => true,
+			}
+		});
+		
+		// a query no longer than max matches even an empty line
+		let (end, target) = found.or((self.len <= self.max).then_some((0, self.len)))?;
+		let mut length = 0;
+		if self.len != target {
+			let before = tokens[..end].iter().rev().map(|token| token.c);
+			self.scan(&self.backward, before, true, |score| {
+				length += 1;
+				score != target
+			});
+		}
+		
+		// a match starting partway into what ß became still takes all of ß
+		let end_byte = if end > 0 { tokens[end - 1].end } else { start };
+		let start_byte = if length > 0 { tokens[end - length].start } else { end_byte };
+		Some((start_byte, end_byte))
+	}
+	
+	pub fn is_match(&self, line: &[u8]) -> bool {
+		self.distance(line).is_some()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	// the textbook table, one row at a time, to check the bit twiddling by
+	fn slow_distance(query: &[char], text: &[char], whole_line: bool) -> usize {
+		let mut row: Vec<usize> = (0..=query.len()).collect();
+		let mut best = row[query.len()];
+		
+		for &c in text {
+			let mut next = vec![if whole_line { row[0] + 1 } else { 0 }];
+			for i in 1..=query.len() {
+				let swap = row[i - 1] + usize::from(query[i - 1] != c);
+				next.push(swap.min(row[i] + 1).min(next[i - 1] + 1));
+			}
+			row = next;
+			best = best.min(row[query.len()]);
+		}
+		
+		if whole_line { row[query.len()] } else { best }
+	}
+	
+	// a little generator so the cases are the same on every run
+	fn words(seed: u64, count: usize, alphabet: &[char]) -> Vec<Vec<char>> {
+		let mut state = seed;
+		let mut next = move || {
+			state ^= state << 13;
+			state ^= state >> 7;
+			state ^= state << 17;
+			state
+		};
+		
+		(0..count)
+			.map(|_| {
+				let len = (next() % 150) as usize;
+				(0..len).map(|_| alphabet[(next() % alphabet.len() as u64) as usize]).collect()
+			})
+			.collect()
+	}
+	
+	#[test]
+	fn agrees_with_the_table() {
+		// queries longer than 64 characters need more than one word per column
+		let alphabet = ['a', 'b', 'c', '\u{e9}'];
+		let queries = words(7, 12, &alphabet);
+		let lines = words(11, 12, &alphabet);
+		
+		for query in &queries {
+			let text: String = query.iter().collect();
+			for whole_line in [false, true] {
+				let fuzzy = Fuzzy::new(&text, usize::MAX, false, whole_line);
+				for line in &lines {
+					let expected = slow_distance(query, line, whole_line);
+					let line: String = line.iter().collect();
+					assert_eq!(Some(expected), fuzzy.distance(line.as_bytes()),
+						"{text:?} in {line:?}");
+				}
+			}
+		}
+	}
+	
+	#[test]
+	fn distances() {
+		let fuzzy = Fuzzy::new("kitten", 3, false, false);
+		assert_eq!(Some(0), fuzzy.distance(b"a kitten sat"));
+		assert_eq!(Some(1), fuzzy.distance(b"a kiten sat"));
+		assert_eq!(Some(2), fuzzy.distance(b"sitting"));
+		assert_eq!(None, fuzzy.distance(b"dog"));
+		
+		// the whole line has to be within reach with -x
+		let fuzzy = Fuzzy::new("kitten", 3, false, true);
+		assert_eq!(Some(3), fuzzy.distance(b"sitting"));
+		assert_eq!(Some(2), fuzzy.distance(b"a kitten"));
+		assert_eq!(None, fuzzy.distance(b"a kitten sat"));
+	}
+	
+	#[test]
+	fn spans() {
+		let fuzzy = Fuzzy::new("hello", 1, false, false);
+		assert_eq!(Some((4, 9)), fuzzy.find_at(b"say hello there", 0));
+		assert_eq!(Some((4, 8)), fuzzy.find_at(b"say helo there", 0));
+		// one deletion away is close enough, even with a better one to come
+		assert_eq!(Some((1, 5)), fuzzy.find_at(b"hello hellow", 1));
+		assert_eq!(None, fuzzy.find_at(b"say hi", 0));
+		
+		// spans are bytes, wherever the characters are more than one
+		let fuzzy = Fuzzy::new("cafe", 1, false, false);
+		assert_eq!(Some((2, 7)), fuzzy.find_at("a caf\u{e9}!".as_bytes(), 0));
+	}
+	
+	#[test]
+	fn ignoring_case() {
+		let fuzzy = Fuzzy::new("STRASSE", 1, true, false);
+		assert_eq!(Some(0), fuzzy.distance("Stra\u{df}e".as_bytes()));
+		assert_eq!(Some(1), fuzzy.distance(b"strase"));
+		assert_eq!(None, Fuzzy::new("STRASSE", 1, false, false).distance(b"strasse"));
+		
+		// half of what ß folds to still takes all of ß
+		let fuzzy = Fuzzy::new("as", 0, true, false);
+		assert_eq!(Some((1, 4)), fuzzy.find_at("Ga\u{df}e".as_bytes(), 0));
+	}
+	
+	#[test]
+	fn odd_input() {
+		let fuzzy = Fuzzy::new("abc", 1, false, false);
+		assert_eq!(Some(1), fuzzy.distance(b"a\xffc"));
+		assert_eq!(Some((0, 3)), fuzzy.find_at(b"a\xffc", 0));
+		assert_eq!(None, fuzzy.distance(b""));
+		
+		// an empty query is always right there
+		assert_eq!(Some(0), Fuzzy::new("", 0, false, false).distance(b"anything"));
+		assert_eq!(Some((0, 0)), Fuzzy::new("", 0, false, false).find_at(b"x", 0));
+	}
+}
@@ -0,0 +1,484 @@
+/*
+ * Ignore files and globs for the directory walk. The rules follow the
+ * .gitignore format: one glob per line, `#` starts a comment, a leading `!`
+ * re-includes something an earlier rule excluded, a trailing `/` only
+ * matches directories, and a pattern with a `/` anywhere but at the end is
+ * anchored to the directory of the file that holds it. Later rules win over
+ * earlier ones, and deeper ignore files win over shallower ones.
+ */
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+// the ignore files looked for in every directory, in increasing priority
+pub const IGNORE_FILES: [&str; 2] = [".gitignore", ".minigrepignore"];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	Char(char),
+	Any,      // ?  one character, but never a '/'
+	Star,     // *  any run of characters without a '/'
+	AnyDirs,  // **/ nothing at all, or any number of whole directories
+	AnyPath,  // /** at the very end, everything inside a directory
+	Class { negated: bool, ranges: Vec<(char, char)> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glob {
+	tokens: Vec<Token>,
+}
+
+impl Glob {
+	pub fn new(pattern: &str) -> Glob {
+		let chars: Vec<char> = pattern.chars().collect();
+		let mut tokens = Vec::new();
+		let mut i = 0;
+		
+		while i < chars.len() {
+			match chars[i] {
+				'*' if chars.get(i + 1) == Some(&'*') => {
+					// ** only means something special next to a '/' or an end
+					let at_start = i == 0 || chars[i - 1] == '/';
+					let next = chars.get(i + 2);
+					
+					if at_start && next == Some(&'/') {
+						tokens.push(Token::AnyDirs);
+						i += 3;
+					} else if at_start && next.is_none() {
+						tokens.push(Token::AnyPath);
+						i += 2;
+					} else {
+						tokens.push(Token::Star);
+						i += 2;
+					}
+				}
+				'*' => {
+					tokens.push(Token::Star);
+					i += 1;
+				}
+				'?' => {
+					tokens.push(Token::Any);
+					i += 1;
+				}
+				'[' => match parse_class(&chars[i + 1..]) {
+					Some((class, used)) => {
+						tokens.push(class);
+						i += 1 + used;
+					}
+					// no closing bracket, so it was just a '['
+					None => {
+						tokens.push(Token::Char('['));
+						i += 1;
+					}
+				},
+				'\\' if i + 1 < chars.len() => {
+					tokens.push(Token::Char(chars[i + 1]));
+					i += 2;
+				}
+				c => {
+					tokens.push(Token::Char(c));
+					i += 1;
+				}
+			}
+		}
+		
+		Glob { tokens }
+	}
+	
+	pub fn is_match(&self, text: &str) -> bool {
+		let text: Vec<char> = text.chars().collect();
+		match_tokens(&self.tokens, &text)
+	}
+}
+
+fn parse_class(chars: &[char]) -> Option<(Token, usize)> {
+	// chars starts just after the '[', returns the class and chars used
+	let mut i = 0;
+	let negated = matches!(chars.first(), Some('!') | Some('^'));
+	if negated {
+		i += 1;
+	}
+	
+	let mut ranges = Vec::new();
+	let mut first = true;
+	
+	while i < chars.len() {
+		let c = chars[i];
+		// a ']' straight after the '[' is a member, not the end
+		if c == ']' && !first {
+			return Some((Token::Class { negated, ranges }, i + 1));
+		}
+		first = false;
+		
+		if chars.get(i + 1) == Some(&'-') && chars.get(i + 2).is_some_and(|&end| end != ']') {
+			ranges.push((c, chars[i + 2]));
+			i += 3;
+		} else {
+			ranges.push((c, c));
+			i += 1;
+		}
+	}
+	
+	None
+}
+
+fn match_tokens(tokens: &[Token], text: &[char]) -> bool {
+	/* plain backtracking, which is plenty for the short names and patterns
+	 * we deal with. each arm either consumes from both sides or tries every
+	 * way a wildcard could stretch.
+	 */
+	let Some((token, rest)) = tokens.split_first() else {
+		return text.is_empty();
+	};
+	
+	match token {
+		Token::Char(c) => text.first() == Some(c) && match_tokens(rest, &text[1..]),
+		Token::Any => text.first().is_some_and(|&c| c != '/') && match_tokens(rest, &text[1..]),
+		Token::Class { negated, ranges } => {
+			text.first().is_some_and(|&c| {
+				let inside = ranges.iter().any(|&(low, high)| low <= c && c <= high);
+				c != '/' && inside != *negated
+			}) && match_tokens(rest, &text[1..])
+		}
+		Token::Star => {
+			// stretch over as much as we like, but never past a '/'
+			let limit = text.iter().position(|&c| c == '/').unwrap_or(text.len());
+			(0..=limit).any(|n| match_tokens(rest, &text[n..]))
+		}
+		Token::AnyDirs => {
+			// either no directories, or skip up to and past any later '/'
+			match_tokens(rest, text)
+				|| text.iter()
+					.enumerate()
+					.filter(|&(_, &c)| c == '/')
+					.any(|(n, _)| match_tokens(rest, &text[n + 1..]))
+		}
+		Token::AnyPath => !text.is_empty(),
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+	glob: Glob,
+	negated: bool,  // a leading ! re-includes
+	dir_only: bool, // a trailing / only matches directories
+	anchored: bool, // matched against the whole relative path, not the name
+}
+
+impl Rule {
+	pub fn parse(line: &str) -> Option<Rule> {
+		// blank lines and comments are no rule at all
+		if line.trim().is_empty() || line.starts_with('#') {
+			return None;
+		}
+		
+		// trailing spaces don't count unless they're escaped
+		let trimmed = line.trim_end();
+		let mut line = if trimmed.ends_with('\\') && line.len() > trimmed.len() {
+			&line[..trimmed.len() + 1]
+		} else {
+			trimmed
+		};
+		
+		let negated = line.starts_with('!');
+		if negated {
+			line = &line[1..];
+		}
+		// "\#" and "\!" stand for a literal leading # or !
+		if line.starts_with("\\#") || line.starts_with("\\!") {
+			line = &line[1..];
+		}
+		
+		let dir_only = line.ends_with('/');
+		let line = line.trim_end_matches('/');
+		if line.is_empty() {
+			return None;
+		}
+		
+		// any '/' left, even a leading one, ties the pattern to its directory
+		let anchored = line.contains('/');
+		let line = line.strip_prefix('/').unwrap_or(line);
+		
+		Some(Rule { glob: Glob::new(line), negated, dir_only, anchored })
+	}
+	
+	fn is_match(&self, relative: &str, is_dir: bool) -> bool {
+		if self.dir_only && !is_dir {
+			return false;
+		}
+		if self.anchored {
+			self.glob.is_match(relative)
+		} else {
+			let name = relative.rsplit('/').next().unwrap_or(relative);
+			self.glob.is_match(name)
+		}
+	}
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IgnoreFile {
+	base: String, // the directory it applies to, "" for the top
+	rules: Vec<Rule>,
+}
+
+impl IgnoreFile {
+	pub fn parse(base: &str, text: &str) -> IgnoreFile {
+		IgnoreFile {
+			base: base.to_string(),
+			rules: text.lines().filter_map(Rule::parse).collect(),
+		}
+	}
+	
+	pub fn matched(&self, path: &str, is_dir: bool) -> Option<bool> {
+		/* Some(true) when the path is ignored, Some(false) when a ! rule let
+		 * it back in, None when no rule here has an opinion. path is relative
+		 * to the same top directory as base.
+		 */
+		let relative = if self.base.is_empty() {
+			path
+		} else {
+			path.strip_prefix(self.base.as_str())?.strip_prefix('/')?
+		};
+		
+		// the last rule that matches gets the final say
+		self.rules.iter()
+			.rev()
+			.find(|rule| rule.is_match(relative, is_dir))
+			.map(|rule| !rule.negated)
+	}
+}
+
+pub struct IgnoreStack {
+	root: PathBuf,     // where the walk started
+	root_in_top: String, // the same place, relative to the top directory
+	files: Vec<IgnoreFile>,
+}
+
+impl IgnoreStack {
+	pub fn new(root: &Path) -> IgnoreStack {
+		/* ignore files above the walk root still count when they belong to
+		 * the same git repository, so find its top and load everything on
+		 * the way down. outside a repository the walk root is the top.
+		 */
+		let mut stack = IgnoreStack {
+			root: root.to_path_buf(),
+			root_in_top: String::new(),
+			files: Vec::new(),
+		};
+		
+		let Ok(canonical) = fs::canonicalize(root) else {
+			return stack;
+		};
+		let Some(top) = canonical.ancestors().find(|dir| dir.join(".git").exists()) else {
+			return stack;
+		};
+		
+		stack.root_in_top = to_slashes(canonical.strip_prefix(top).unwrap_or(Path::new("")));
+		for dir in canonical.ancestors().skip(1) {
+			if !dir.starts_with(top) {
+				break;
+			}
+			let base = to_slashes(dir.strip_prefix(top).unwrap_or(Path::new("")));
+			// ancestors come innermost first, so each goes in at the front
+			for file in load(dir, &base).into_iter().rev() {
+				stack.files.insert(0, file);
+			}
+		}
+		
+		stack
+	}
+	
+	fn relative(&self, path: &Path) -> String {
+		let inside = to_slashes(path.strip_prefix(&self.root).unwrap_or(path));
+		match (self.root_in_top.is_empty(), inside.is_empty()) {
+			(true, _) => inside,
+			(false, true) => self.root_in_top.clone(),
+			(false, false) => format!("{}/{inside}", self.root_in_top),
+		}
+	}
+	
+	pub fn push_dir(&mut self, dir: &Path) -> usize {
+		// returns how many files were pushed, to hand back to pop
+		let base = self.relative(dir);
+		let loaded = load(dir, &base);
+		let pushed = loaded.len();
+		self.files.extend(loaded);
+		pushed
+	}
+	
+	pub fn pop(&mut self, count: usize) {
+		self.files.truncate(self.files.len() - count);
+	}
+	
+	pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		let relative = self.relative(path);
+		
+		// the deepest file with an opinion wins
+		self.files.iter()
+			.rev()
+			.find_map(|file| file.matched(&relative, is_dir))
+			.unwrap_or(false)
+	}
+}
+
+fn load(dir: &Path, base: &str) -> Vec<IgnoreFile> {
+	IGNORE_FILES.iter()
+		.filter_map(|name| fs::read_to_string(dir.join(name)).ok())
+		.map(|text| IgnoreFile::parse(base, &text))
+		.collect()
+}
+
+pub fn to_slashes(path: &Path) -> String {
+	// gitignore paths always use '/', whatever the platform does
+	path.components()
+		.filter_map(|part| match part {
+			Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+			_ => None,
+		})
+		.collect::<Vec<_>>()
+		.join("/")
+}
+
+pub struct Filter {
+	pub ignore_files: bool, // respect .gitignore and .minigrepignore
+	pub hidden: bool,       // walk into dotfiles and dot-directories
+	pub include: Vec<Glob>,
+	pub exclude: Vec<Glob>,
+}
+
+impl Filter {
+	pub fn everything() -> Filter {
+		Filter { ignore_files: false, hidden: true, include: Vec::new(), exclude: Vec::new() }
+	}
+	
+	fn glob_matches(glob: &Glob, relative: &str) -> bool {
+		// like grep's --include, a glob without a '/' is about the name only
+		let name = relative.rsplit('/').next().unwrap_or(relative);
+		glob.is_match(name) || glob.is_match(relative)
+	}
+	
+	pub fn wants_file(&self, relative: &str) -> bool {
+		let included = self.include.is_empty()
+			|| self.include.iter().any(|glob| Filter::glob_matches(glob, relative));
+		included && !self.excludes(relative)
+	}
+	
+	pub fn excludes(&self, relative: &str) -> bool {
+		self.exclude.iter().any(|glob| Filter::glob_matches(glob, relative))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	fn glob(pattern: &str, text: &str) -> bool {
+		Glob::new(pattern).is_match(text)
+	}
+	
+	#[test]
+	fn glob_wildcards() {
+		assert!(glob("*.rs", "lib.rs"));
+		assert!(!glob("*.rs", "src/lib.rs"));
+		assert!(glob("?oem.txt", "poem.txt"));
+		assert!(!glob("?oem.txt", "/oem.txt"));
+		assert!(glob("lib.[rt]s", "lib.ts"));
+		assert!(!glob("lib.[!rt]s", "lib.rs"));
+		assert!(glob("[a-c]*", "build"));
+		assert!(glob("[]x]", "]"));
+		assert!(glob("unclosed[", "unclosed["));
+		assert!(glob(r"\*", "*"));
+		assert!(!glob(r"\*", "x"));
+	}
+	
+	#[test]
+	fn glob_double_star() {
+		assert!(glob("**/foo", "foo"));
+		assert!(glob("**/foo", "a/b/foo"));
+		assert!(glob("a/**/b", "a/b"));
+		assert!(glob("a/**/b", "a/x/y/b"));
+		assert!(!glob("a/**/b", "ab"));
+		assert!(glob("target/**", "target/debug/minigrep"));
+		assert!(!glob("target/**", "target"));
+		// anywhere else it's a plain *
+		assert!(glob("a**b", "axyzb"));
+		assert!(!glob("a**b", "a/b"));
+	}
+	
+	#[test]
+	fn rules_parse() {
+		assert_eq!(None, Rule::parse("# a comment"));
+		assert_eq!(None, Rule::parse("   "));
+		assert_eq!(None, Rule::parse("/"));
+		
+		let rule = Rule::parse("!/build/ ").unwrap();
+		assert!(rule.negated && rule.dir_only && rule.anchored);
+		assert_eq!(Glob::new("build"), rule.glob);
+		
+		let rule = Rule::parse(r"\#not-a-comment").unwrap();
+		assert!(!rule.negated && !rule.anchored);
+		assert_eq!(Glob::new("#not-a-comment"), rule.glob);
+		
+		assert!(!Rule::parse(r"\!important").unwrap().negated);
+		assert_eq!(Glob::new("trailing "), Rule::parse("trailing\\  ").unwrap().glob);
+		assert!(Rule::parse("docs/*.md").unwrap().anchored);
+	}
+	
+	#[test]
+	fn negation() {
+		let file = IgnoreFile::parse("", "*.log\n!keep.log\n");
+		
+		assert_eq!(Some(true), file.matched("debug.log", false));
+		assert_eq!(Some(false), file.matched("keep.log", false));
+		assert_eq!(Some(false), file.matched("deep/keep.log", false));
+		assert_eq!(None, file.matched("poem.txt", false));
+		
+		// the order of the rules matters, the last match wins
+		let file = IgnoreFile::parse("", "!keep.log\n*.log\n");
+		assert_eq!(Some(true), file.matched("keep.log", false));
+	}
+	
+	#[test]
+	fn directory_only() {
+		let file = IgnoreFile::parse("", "target/\n");
+		
+		assert_eq!(Some(true), file.matched("target", true));
+		assert_eq!(Some(true), file.matched("nested/target", true));
+		assert_eq!(None, file.matched("target", false));
+	}
+	
+	#[test]
+	fn anchored() {
+		let file = IgnoreFile::parse("", "/poem.txt\nsrc/*.bak\n");
+		
+		assert_eq!(Some(true), file.matched("poem.txt", false));
+		assert_eq!(None, file.matched("sub/poem.txt", false));
+		assert_eq!(Some(true), file.matched("src/lib.bak", false));
+		assert_eq!(None, file.matched("src/deep/lib.bak", false));
+		assert_eq!(None, file.matched("other/src/lib.bak", false));
+	}
+	
+	#[test]
+	fn nested_base() {
+		// a file in sub/ only speaks for paths under sub/
+		let file = IgnoreFile::parse("sub", "/only-here\n");
+		
+		assert_eq!(Some(true), file.matched("sub/only-here", false));
+		assert_eq!(None, file.matched("only-here", false));
+		assert_eq!(None, file.matched("subway/only-here", false));
+	}
+	
+	#[test]
+	fn filter_globs() {
+		let filter = Filter {
+			include: vec![Glob::new("*.rs"), Glob::new("*.txt")],
+			exclude: vec![Glob::new("poem*"), Glob::new("tests/*")],
+			..Filter::everything()
+		};
+		
+		assert!(filter.wants_file("src/lib.rs"));
+		assert!(!filter.wants_file("Cargo.toml"));
+		assert!(!filter.wants_file("poem.txt"));
+		assert!(!filter.wants_file("tests/json.rs"));
+	}
+}
@@ -0,0 +1,145 @@
+/*
+ * Hand-rolled JSON Lines output for --json, one object per line. Only
+ * strings and numbers are ever written, so this is all we need and no JSON
+ * crate ends up in minigrep's public API. The shapes are:
+ *
+ * {"type":"begin","path":P}
+ * {"type":"match","path":P,"line_number":N,"byte_offset":N,"line":S,
+ *  "submatches":[{"start":N,"end":N,"text":S}]}
+ * {"type":"context", ...the same as match, with no submatches}
+ * {"type":"end","path":P,"matched_lines":N}
+ * {"type":"summary","searched":N,"files_with_matches":N,"matched_lines":N}
+ *
+ * start and end are byte offsets into the original line. a line, or a
+ * submatch, that isn't valid UTF-8 can't be a JSON string without losing
+ * bytes those offsets count, so S is then {"bytes":B} instead, with B the
+ * exact bytes in base64.
+ */
+
+use crate::ByteLine;
+
+pub(crate) fn string(text: &str) -> String {
+	let mut quoted = String::with_capacity(text.len() + 2);
+	quoted.push('"');
+	
+	for c in text.chars() {
+		match c {
+			'"' => quoted.push_str("\\\""),
+			'\\' => quoted.push_str("\\\\"),
+			'\n' => quoted.push_str("\\n"),
+			'\r' => quoted.push_str("\\r"),
+			'\t' => quoted.push_str("\\t"),
+			// any other control character has to be escaped by number
+			c if c < ' ' => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+			c => quoted.push(c),
+		}
+	}
+	
+	quoted.push('"');
+	quoted
+}
+
+fn bytes(text: &[u8]) -> String {
+	match std::str::from_utf8(text) {
+		Ok(text) => string(text),
+		Err(_) => format!(r#"{{"bytes":"{}"}}"#, base64(text)),
+	}
+}
+
+fn base64(bytes: &[u8]) -> String {
+	const DIGITS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+	let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	
+	for chunk in bytes.chunks(3) {
+		// three bytes are four digits of six bits, a short last chunk is padded with '='
+		let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+		for i in 0..4 {
+			if i <= chunk.len() {
+				encoded.push(DIGITS[(n >> (18 - 6 * i) & 63) as usize] as char);
+			} else {
+				encoded.push('=');
+			}
+		}
+	}
+	
+	encoded
+}
+
+pub(crate) fn begin(path: &str) -> String {
+	format!(r#"{{"type":"begin","path":{}}}"#, string(path))
+}
+
+pub(crate) fn line(
+	kind: &str, path: &str, line: &ByteLine, submatches: &[(usize, usize)]
+) -> String {
+	let submatches: Vec<String> = submatches
+		.iter()
+		.map(|&(start, end)| format!(
+			r#"{{"start":{start},"end":{end},"text":{}}}"#,
+			bytes(&line.bytes[start..end])
+		))
+		.collect();
+	
+	format!(
+		r#"{{"type":"{kind}","path":{},"line_number":{},"byte_offset":{},"line":{},"submatches":[{}]}}"#,
+		string(path),
+		line.line_number,
+		line.byte_offset,
+		bytes(line.bytes),
+		submatches.join(",")
+	)
+}
+
+pub(crate) fn end(path: &str, matched_lines: usize) -> String {
+	format!(r#"{{"type":"end","path":{},"matched_lines":{matched_lines}}}"#, string(path))
+}
+
+pub(crate) fn summary(searched: usize, files_with_matches: usize, matched_lines: usize) -> String {
+	format!(
+		r#"{{"type":"summary","searched":{searched},"files_with_matches":{files_with_matches},"matched_lines":{matched_lines}}}"#
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	#[test]
+	fn escapes_strings() {
+		assert_eq!(r#""plain""#, string("plain"));
+		assert_eq!(r#""say \"hi\"\\n""#, string("say \"hi\"\\n"));
+		assert_eq!(r#""tab\tnew\nline\u0007""#, string("tab\tnew\nline\u{7}"));
+		// anything printable goes through as is
+		assert_eq!("\"caf\u{e9}\"", string("caf\u{e9}"));
+	}
+	
+	#[test]
+	fn line_with_submatches() {
+		let found = ByteLine { line_number: 13, byte_offset: 148, bytes: b"How public, like a frog" };
+		
+		assert_eq!(
+			r#"{"type":"match","path":"poem.txt","line_number":13,"byte_offset":148,"line":"How public, like a frog","submatches":[{"start":19,"end":23,"text":"frog"}]}"#,
+			line("match", "poem.txt", &found, &[(19, 23)])
+		);
+	}
+	
+	#[test]
+	fn invalid_utf8_as_bytes() {
+		let found = ByteLine { line_number: 1, byte_offset: 0, bytes: b"caf\xe9 frog" };
+		let event = line("match", "-", &found, &[(0, 4), (5, 9)]);
+		
+		// every byte is kept, so the offsets still line up with the line
+		assert!(event.contains(r#""line":{"bytes":"Y2Fm6SBmcm9n"}"#), "{event}");
+		assert!(event.contains(r#"{"start":0,"end":4,"text":{"bytes":"Y2Fm6Q=="}}"#), "{event}");
+		assert!(event.contains(r#"{"start":5,"end":9,"text":"frog"}"#), "{event}");
+	}
+	
+	#[test]
+	fn base64_padding() {
+		assert_eq!("", base64(b""));
+		assert_eq!("Zg==", base64(b"f"));
+		assert_eq!("Zm8=", base64(b"fo"));
+		assert_eq!("Zm9v", base64(b"foo"));
+		assert_eq!("/+8A", base64(b"\xff\xef\x00"));
+	}
+}
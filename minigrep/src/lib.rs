@@ -1,67 +1,172 @@
 use std::fs; // filesystem manipulation
 use std::error::Error;
-use std::env; // for environment variables
-
-pub struct Config {
-    pub query: String,
-    pub file_path: String,
-    pub ignore_case: bool,
-    pub verbose: bool,
-    pub count: bool,
-}
-
-impl Config {
-    pub fn new(
-        mut args: impl Iterator<Item = String> + DoubleEndedIterator,
-        n: usize,
-    ) -> Result<Config, &'static str> {
-		/* new now uses iterators to construct the config from args.
-		 * included ` + DoubleEndedIterator` to allow for `next_back`.
-		 */
-		let query: String;
-		let file_path: String;
-		let mut ignore_case: bool;
-		let mut verbose: bool;
-		let mut count: bool;
-		
-		if n < 3 {
-            // error values will be string literals with 'static lifetime
-            return Err("not enough arguments provided");
-        }
-		
-		// env::var returns a Result, is_ok returns bool 
-        ignore_case = env::var("IGNORE_CASE").is_ok();
-        verbose = env::var("VERBOSE").is_ok();
-        count = env::var("COUNT").is_ok();
-		
-		file_path = match args.next_back() { // consume from the end
-            Some(arg) => arg,
-            None => return Err("Didn't get a file path"),
-        };
-        
-        query = match args.next_back() {
-            Some(arg) => arg,
-            None => return Err("Didn't get a query string"),
-        };
-        
-        args.next();
-		
-		while let Some(arg) = args.next() {
-			match arg.as_str() {
-				"-i" | "--ignore-case" => ignore_case = true,
-				"-v" | "--verbose" => verbose = true,
-				"-c" | "--count" => count = true,
-				_ => return Err("unrecognized argument"),
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::Ordering;
+
+use regex::{Regex, RegexBuilder};
+
+use ignore::IgnoreStack;
+
+mod binary;
+mod color;
+mod config;
+mod decompress;
+mod defaults;
+mod encoding;
+mod finder;
+mod follow;
+mod fold;
+mod fuzzy;
+mod ignore;
+mod json;
+mod matcher;
+mod multiline;
+mod parallel;
+mod replace;
+mod searcher;
+mod stream;
+
+pub use binary::BinaryFiles;
+pub use color::ColorChoice;
+pub use ignore::{Filter, Glob};
+pub use config::{Config, ConfigError};
+pub use encoding_rs::Encoding;
+pub use finder::Finder;
+pub use fuzzy::Fuzzy;
+pub use matcher::{Matcher, PatternMatcher};
+pub use searcher::{Finish, SearchError, Searcher, Sink, SinkMatch};
+pub use stream::{search_reader, ByteLine, Event};
+
+// where a search reads its text from
+enum Input {
+	Stdin,
+	File(PathBuf),
+}
+
+impl Input {
+	fn name(&self) -> String {
+		match self {
+			Input::Stdin => String::from("(standard input)"),
+			Input::File(path) => path.display().to_string(),
+		}
+	}
+	
+	fn open(&self) -> io::Result<Box<dyn BufRead>> {
+		// a buffered reader lets us go line by line without reading it all
+		match self {
+			Input::Stdin => Ok(Box::new(io::stdin().lock())),
+			Input::File(path) => Ok(Box::new(BufReader::new(File::open(path)?))),
+		}
+	}
+	
+	fn follow(&self) -> io::Result<Box<dyn BufRead>> {
+		// standard input already waits for more until whoever writes it is done
+		match self {
+			Input::Stdin => self.open(),
+			Input::File(path) => {
+				let tail = follow::Tail::open(path.clone(), follow::POLL, &follow::INTERRUPTED)?;
+				Ok(Box::new(BufReader::new(tail)))
 			}
 		}
-		
-        Ok(Config { query, file_path, ignore_case, verbose, count })
-    }
+	}
 }
 
 #[cfg(test)]
 mod tests{
 	use super::*;
+	use std::env;
+	
+	/*
+	 * The line-at-a-time search over a whole &str that minigrep began with,
+	 * kept as an oracle: it's simple enough to be obviously right, so the
+	 * streaming search in stream.rs and the Searcher are checked against it.
+	 */
+	
+	pub(crate) fn search_regex<'a>(pattern: &Regex, contents: &'a str) -> Vec<&'a str> {
+		contents.lines()
+				.filter(|line| pattern.is_match(line))
+				.collect()
+	}
+	
+	#[derive(Debug, PartialEq, Clone, Copy)]
+	pub(crate) struct LineMatch<'a> {
+		pub(crate) line_number: usize, // counting from 1, the way editors do
+		pub(crate) byte_offset: usize, // where the line starts within the contents
+		pub(crate) line: &'a str,
+	}
+	
+	fn positioned_lines(contents: &str) -> impl Iterator<Item = LineMatch<'_>> {
+		/* like contents.lines(), but remembering where each line came from.
+		 * split_inclusive keeps the '\n' so the offsets stay exact.
+		 */
+		let mut byte_offset = 0;
+		contents.split_inclusive('\n').enumerate().map(move |(i, raw)| {
+			let start = byte_offset;
+			byte_offset += raw.len();
+			
+			// strip the terminator the same way lines() does, "\r\n" included
+			let line = match raw.strip_suffix('\n') {
+				Some(line) => line.strip_suffix('\r').unwrap_or(line),
+				None => raw,
+			};
+			
+			LineMatch { line_number: i + 1, byte_offset: start, line }
+		})
+	}
+	
+	pub(crate) fn search_positions<'a>(
+		contents: &'a str, is_match: impl Fn(&str) -> bool
+	) -> Vec<LineMatch<'a>> {
+		// the caller decides what a match is, we keep track of where it was
+		positioned_lines(contents)
+			.filter(|found| is_match(found.line))
+			.collect()
+	}
+	
+	pub(crate) fn context_groups<'a>(
+		contents: &'a str, results: &[LineMatch<'a>], before: usize, after: usize
+	) -> Vec<Vec<(LineMatch<'a>, bool)>> {
+		/* mark every line that should be shown: Some(true) for a match and
+		 * Some(false) for context. overlapping windows merge on their own since
+		 * they mark the same lines, and each unbroken run becomes one group.
+		 */
+		let lines: Vec<LineMatch> = positioned_lines(contents).collect();
+		let mut shown: Vec<Option<bool>> = vec![None; lines.len()];
+		
+		// results from some other contents could point past the end
+		for found in results.iter().filter(|found| found.line_number <= lines.len()) {
+			let i = found.line_number - 1;
+			let first = i.saturating_sub(before);
+			let last = (i + after).min(lines.len() - 1);
+			
+			for slot in &mut shown[first..=last] {
+				slot.get_or_insert(false); // never demote a match to context
+			}
+			shown[i] = Some(true);
+		}
+		
+		let mut groups: Vec<Vec<(LineMatch, bool)>> = Vec::new();
+		let mut in_group = false;
+		
+		for (line, shown) in lines.into_iter().zip(shown) {
+			match shown {
+				Some(is_match) => {
+					if !in_group {
+						groups.push(Vec::new());
+						in_group = true;
+					}
+					// unwrap is safe, we just made sure a group exists
+					groups.last_mut().unwrap().push((line, is_match));
+				}
+				None => in_group = false,
+			}
+		}
+		
+		groups
+	}
 	
 	#[test]
 	fn case_sensitive() {
@@ -71,57 +176,540 @@ Rust:
 safe, fast, productive.
 Pick three.
 Duct tape.";
-
+		
 		assert_eq!(vec!["safe, fast, productive."], search(query, contents));
 	}
 	
 	
-    #[test]
-    fn case_insensitive() {
-        let query = "rUsT";
-        let contents = "\
+	#[test]
+	fn case_insensitive() {
+		let query = "rUsT";
+		let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+
+		assert_eq!(
+			vec!["Rust:", "Trust me."],
+			search_case_insensitive(query, contents)
+		);
+	}
+	
+	#[test]
+	fn multiline() {
+		let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+		
+		let pattern = build_regex(r"fast, productive\.\nPick", false).unwrap();
+		assert_eq!(vec!["safe, fast, productive.\nPick three."], search_multiline(&pattern, contents));
+		
+		// one entry per match, even when two share a line
+		let pattern = build_regex(r"(?s)Rust.+?safe|st", true).unwrap();
+		assert_eq!(
+			vec!["Rust:\nsafe, fast, productive.", "safe, fast, productive.", "Trust me."],
+			search_multiline(&pattern, contents)
+		);
+		
+		// line by line, the first of these could never have been found
+		assert!(search_regex(&build_regex(r":\ns", false).unwrap(), contents).is_empty());
+		assert_eq!(1, search_multiline(&build_regex(r":\ns", false).unwrap(), contents).len());
+	}
+	
+	#[test]
+	fn fuzzy() {
+		let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.";
+		
+		// one dropped letter, one swapped, and one that's too far gone
+		assert_eq!(vec!["safe, fast, productive."], search_fuzzy("prodctive", contents, 1));
+		assert_eq!(vec!["Pick three."], search_fuzzy("Pock", contents, 1));
+		assert!(search_fuzzy("Pork", contents, 1).is_empty());
+		assert_eq!(vec!["Pick three."], search_fuzzy("Pork", contents, 2));
+		
+		// no edits at all is the same as search
+		assert_eq!(search("rust", contents), search_fuzzy("rust", contents, 0));
+	}
+	
+	#[test]
+	fn fuzzy_matcher_distances() {
+		let matcher = |list: &[&str]| {
+			let mut all = vec!["minigrep"];
+			all.extend(list);
+			PatternMatcher::new(&Config::from_args(args(&all)).unwrap()).unwrap()
+		};
+		
+		let matcher_2 = matcher(&["--fuzzy", "2", "-e", "frog", "-e", "bog"]);
+		assert_eq!(Some(0), matcher_2.distance(b"How public, like a frog"));
+		assert_eq!(Some(1), matcher_2.distance(b"a frg"));
+		assert_eq!(None, matcher_2.distance(b"xyz"));
+		assert_eq!(vec![(2, 5), (6, 8)], matcher_2.find_all(b"a big bg"));
+		
+		// -i folds both sides, and -w wants the match to be a whole word
+		let whole_words = matcher(&["-i", "-w", "--fuzzy=1", "FROG"]);
+		assert!(whole_words.is_match(b"a Frig"));
+		assert!(!whole_words.is_match(b"a Frigate"));
+		
+		// the exact engines don't deal in distances
+		assert_eq!(None, matcher(&["frog"]).distance(b"frog"));
+	}
+	
+	// the same few lines in several languages, for the case folding tests
+	const MULTILINGUAL: &str = "\
+Die Stra\u{df}e ist lang.
+DIE STRASSE IST LANG.
+\u{130}stanbul'a gidiyoruz.
+Diyarbak\u{131}r uzak.
+\u{39f}\u{394}\u{3a5}\u{3a3}\u{3a3}\u{395}\u{3a5}\u{3a3} \u{3ba}\u{3b1}\u{3b9} \u{3c3}\u{3bf}\u{3c6}\u{3cc}\u{3c2}
+\u{41c}\u{43e}\u{441}\u{43a}\u{432}\u{430} \u{2014} \u{421}\u{422}\u{41e}\u{41b}\u{418}\u{426}\u{410}
+The o\u{fb03}ce is 300 \u{212a} warm.";
+
+	#[test]
+	fn case_insensitive_full_folding() {
+		// ß is "ss" once folded, whichever side of the search it's on
+		assert_eq!(
+			vec!["Die Stra\u{df}e ist lang.", "DIE STRASSE IST LANG."],
+			search_case_insensitive("strasse", MULTILINGUAL)
+		);
+		assert_eq!(
+			vec!["Die Stra\u{df}e ist lang.", "DIE STRASSE IST LANG."],
+			search_case_insensitive("STRA\u{df}E", MULTILINGUAL)
+		);
+		// the final sigma is just a sigma
+		assert_eq!(
+			vec!["\u{39f}\u{394}\u{3a5}\u{3a3}\u{3a3}\u{395}\u{3a5}\u{3a3} \u{3ba}\u{3b1}\u{3b9} \u{3c3}\u{3bf}\u{3c6}\u{3cc}\u{3c2}"],
+			search_case_insensitive("\u{3bf}\u{3b4}\u{3c5}\u{3c3}\u{3c3}\u{3b5}\u{3c5}\u{3c3}", MULTILINGUAL)
+		);
+		assert_eq!(1, search_case_insensitive("\u{441}\u{442}\u{43e}\u{43b}\u{438}\u{446}\u{430}", MULTILINGUAL).len());
+		assert_eq!(vec!["The o\u{fb03}ce is 300 \u{212a} warm."],
+			search_case_insensitive("OFFICE IS 300 K", MULTILINGUAL));
+	}
+
+	#[test]
+	fn case_insensitive_turkish_i() {
+		// İ folds to i with a combining dot above, not to a plain i
+		assert_eq!(vec!["\u{130}stanbul'a gidiyoruz."],
+			search_case_insensitive("i\u{307}STANBUL", MULTILINGUAL));
+		assert!(search_case_insensitive("istanbul", MULTILINGUAL).is_empty());
+		// and without Turkish rules the dotless ı stays apart from I and i
+		assert_eq!(vec!["Diyarbak\u{131}r uzak."],
+			search_case_insensitive("DIYARBAK\u{131}R", MULTILINGUAL));
+		assert!(search_case_insensitive("DIYARBAKIR", MULTILINGUAL).is_empty());
+	}
+
+	fn run_matcher(flags: &[&str], pattern: &str) -> Vec<&'static str> {
+		let mut list = vec!["minigrep"];
+		list.extend(flags);
+		list.push(pattern);
+		let matcher = PatternMatcher::new(&Config::from_args(args(&list)).unwrap()).unwrap();
+		
+		MULTILINGUAL.lines().filter(|line| matcher.is_match(line.as_bytes())).collect()
+	}
+
+	#[test]
+	fn matcher_full_folding() {
+		assert_eq!(2, run_matcher(&["-i"], "strasse").len());
+		assert_eq!(2, run_matcher(&["-i", "-w"], "STRA\u{df}E").len());
+		assert_eq!(vec!["The o\u{fb03}ce is 300 \u{212a} warm."],
+			run_matcher(&["-i"], "Office"));
+		// -E gets the regex engine's simple folding, so ß isn't "ss" there
+		assert_eq!(vec!["Die Stra\u{df}e ist lang."], run_matcher(&["-i", "-E"], "stra\u{df}e"));
+	}
+
+	#[test]
+	fn smart_case() {
+		// all lowercase, so case doesn't matter
+		assert_eq!(2, run_matcher(&["-S"], "stra\u{df}e").len());
+		assert_eq!(2, run_matcher(&["-S"], "ist lang").len());
+		// a capital, even one that isn't ASCII, means exactly that
+		assert_eq!(vec!["DIE STRASSE IST LANG."], run_matcher(&["-S"], "STRASSE"));
+		assert_eq!(1, run_matcher(&["-S"], "\u{421}\u{422}\u{41e}").len());
+		assert!(run_matcher(&["-S"], "\u{421}\u{442}\u{43e}").is_empty());
+		
+		// the last of -i and -S wins
+		assert_eq!(2, run_matcher(&["-S", "-i"], "STRASSE").len());
+		assert_eq!(1, run_matcher(&["-i", "-S"], "STRASSE").len());
+	}
+
+	#[test]
+	fn regex_classes_and_anchors() {
+		let pattern = build_regex(r"^[A-Z]\w+ (tape|three)\.$", false).unwrap();
+		let contents = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Duct tape.
+pick three.";
+
+		assert_eq!(
+			vec!["Pick three.", "Duct tape."],
+			search_regex(&pattern, contents)
+		);
+	}
+
+	#[test]
+	fn regex_ignore_case() {
+		let pattern = build_regex("ru+st|PICK", true).unwrap();
+		let contents = "\
 Rust:
 safe, fast, productive.
 Pick three.
 Trust me.";
 
-        assert_eq!(
-            vec!["Rust:", "Trust me."],
-            search_case_insensitive(query, contents)
-        );
-    }
+		assert_eq!(
+			vec!["Rust:", "Pick three.", "Trust me."],
+			search_regex(&pattern, contents)
+		);
+	}
+
+	#[test]
+	fn regex_invalid_pattern() {
+		assert!(build_regex("(unclosed", false).is_err());
+	}
+
+	#[test]
+	fn walk_recurses_in_order() {
+		let root = env::temp_dir().join(format!("minigrep_walk_recurses_in_order_{}", process::id()));
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(root.join("b/nested")).unwrap();
+		fs::write(root.join("c.txt"), "c").unwrap();
+		fs::write(root.join("a.txt"), "a").unwrap();
+		fs::write(root.join("b/nested/d.txt"), "d").unwrap();
+		
+		assert_eq!(
+			vec![
+				root.join("a.txt"),
+				root.join("b/nested/d.txt"),
+				root.join("c.txt"),
+			],
+			walk(&root)
+		);
+		
+		fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn walk_respects_ignore_files() {
+		let root = env::temp_dir().join(format!("minigrep_walk_respects_ignore_files_{}", process::id()));
+		let _ = fs::remove_dir_all(&root);
+		fs::create_dir_all(root.join(".git")).unwrap();
+		fs::create_dir_all(root.join("src/target")).unwrap();
+		fs::create_dir_all(root.join("target")).unwrap();
+		fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+		fs::write(root.join("src/.minigrepignore"), "!keep.log\n").unwrap();
+		fs::write(root.join(".git/HEAD"), "ref").unwrap();
+		fs::write(root.join(".hidden.txt"), "h").unwrap();
+		fs::write(root.join("a.txt"), "a").unwrap();
+		fs::write(root.join("a.log"), "a").unwrap();
+		fs::write(root.join("src/keep.log"), "k").unwrap();
+		fs::write(root.join("src/lib.rs"), "l").unwrap();
+		fs::write(root.join("src/target/out.txt"), "o").unwrap();
+		fs::write(root.join("target/out.txt"), "o").unwrap();
+		
+		let defaults = Filter { ignore_files: true, hidden: false, ..Filter::everything() };
+		assert_eq!(
+			vec![root.join("a.txt"), root.join("src/keep.log"), root.join("src/lib.rs")],
+			walk_with(&root, &defaults)
+		);
+		
+		// starting lower down still sees the .gitignore at the top
+		assert_eq!(
+			vec![root.join("src/keep.log"), root.join("src/lib.rs")],
+			walk_with(&root.join("src"), &defaults)
+		);
+		
+		let globs = Filter {
+			include: vec![Glob::new("*.txt")],
+			exclude: vec![Glob::new("src")],
+			..Filter::everything()
+		};
+		assert_eq!(
+			vec![root.join(".hidden.txt"), root.join("a.txt"), root.join("target/out.txt")],
+			walk_with(&root, &globs)
+		);
+		
+		fs::remove_dir_all(&root).unwrap();
+	}
+
+	#[test]
+	fn positions_of_matches() {
+		let contents = "\
+Rust:
+safe, fast, productive.\r
+Pick three.
+Duct tape.";
+		
+		assert_eq!(
+			vec![
+				LineMatch { line_number: 2, byte_offset: 6, line: "safe, fast, productive." },
+				LineMatch { line_number: 4, byte_offset: 43, line: "Duct tape." },
+			],
+			search_positions(contents, |line| line.to_lowercase().contains("duct"))
+		);
+	}
+
+	#[test]
+	fn positions_agree_with_search() {
+		let query = "e";
+		let contents = "\
+Rust:
+safe, fast, productive.
+
+Pick three.
+Duct tape.
+";
+		let lines: Vec<&str> = search_positions(contents, |line| line.contains(query))
+			.into_iter()
+			.map(|found| found.line)
+			.collect();
+		
+		assert_eq!(search(query, contents), lines);
+	}
+
+	fn group_lines(groups: &[Vec<(LineMatch, bool)>]) -> Vec<Vec<(usize, bool)>> {
+		groups.iter()
+			.map(|group| group.iter().map(|(l, m)| (l.line_number, *m)).collect())
+			.collect()
+	}
+
+	#[test]
+	fn context_merges_overlapping_windows() {
+		let contents = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine";
+		let results = search_positions(contents, |line| line == "three" || line == "five");
+		
+		assert_eq!(
+			vec![vec![(2, false), (3, true), (4, false), (5, true), (6, false)]],
+			group_lines(&context_groups(contents, &results, 1, 1))
+		);
+	}
+
+	#[test]
+	fn context_splits_distant_groups() {
+		let contents = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine";
+		let results = search_positions(contents, |line| line == "one" || line == "nine");
+		
+		assert_eq!(
+			vec![vec![(1, true), (2, false)], vec![(8, false), (9, true)]],
+			group_lines(&context_groups(contents, &results, 1, 1))
+		);
+		// before and after are independent of each other
+		assert_eq!(
+			vec![vec![(1, true), (2, false), (3, false)], vec![(9, true)]],
+			group_lines(&context_groups(contents, &results, 0, 2))
+		);
+		// results that don't belong to the contents are no lines at all
+		assert!(context_groups("", &results, 1, 1).is_empty());
+	}
+
+	#[test]
+	fn config_context_lengths() {
+		let config = Config::from_args(
+			args(&["minigrep", "-C", "3", "-A", "1", "to", "a.txt"])
+		).unwrap();
+		
+		assert_eq!(3, config.before_context);
+		assert_eq!(1, config.after_context);
+		assert!(Config::from_args(args(&["minigrep", "-B", "x", "to"])).is_err());
+	}
+
+	#[test]
+	fn color_highlights_matches() {
+		let config = Config::from_args(
+			args(&["minigrep", "--color=always", "-n", "-i", "o", "poem.txt"])
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		let line = ByteLine { line_number: 3, byte_offset: 26, bytes: b"Are you nobody, too?" };
+		let mut out = Vec::new();
+		write_line(&mut out, &config, &matcher, Some("poem.txt"), &line, ':', None).unwrap();
+		
+		let red = |text| format!("\x1b[01;31m{text}\x1b[0m");
+		assert_eq!(
+			format!(
+				"\x1b[35mpoem.txt\x1b[0m\x1b[36m:\x1b[0m\x1b[32m3\x1b[0m\x1b[36m:\x1b[0m\
+				Are y{}u n{}b{}dy, t{}{}?\n",
+				red("o"), red("o"), red("o"), red("o"), red("o")
+			),
+			String::from_utf8(out).unwrap()
+		);
+	}
+
+	#[test]
+	fn color_never_is_plain() {
+		let config = Config::from_args(
+			args(&["minigrep", "--color=never", "-n", "o", "poem.txt"])
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		let line = ByteLine { line_number: 3, byte_offset: 26, bytes: b"Are you nobody, too?" };
+		let mut out = Vec::new();
+		write_line(&mut out, &config, &matcher, None, &line, ':', None).unwrap();
+		
+		assert_eq!("3:Are you nobody, too?\n", String::from_utf8(out).unwrap());
+		assert!(Config::from_args(args(&["minigrep", "--color=maybe", "o"])).is_err());
+	}
+
+	fn args(list: &[&str]) -> impl Iterator<Item = String> {
+		let list: Vec<String> = list.iter().map(|arg| arg.to_string()).collect();
+		list.into_iter()
+	}
+
+	#[test]
+	fn config_many_files() {
+		let config = Config::from_args(
+			args(&["minigrep", "-c", "to", "a.txt", "-", "b.txt"])
+		).unwrap();
+		
+		assert!(config.count);
+		assert_eq!(vec!["to"], config.patterns);
+		assert_eq!(vec!["a.txt", "-", "b.txt"], config.file_paths);
+	}
+
+	#[test]
+	fn config_no_files_means_stdin() {
+		let config = Config::from_args(args(&["minigrep", "to"])).unwrap();
+		
+		assert_eq!(vec!["to"], config.patterns);
+		assert!(config.file_paths.is_empty());
+	}
+
+	#[test]
+	fn config_needs_a_query() {
+		assert!(Config::from_args(args(&["minigrep", "-i"])).is_err());
+		assert!(Config::from_args(args(&["minigrep", "--bogus", "to"])).is_err());
+	}
 
-    #[test]
-    fn is_count() {
+	#[test]
+	fn is_count() {
 		// invent results
-        let results: Vec<&str> = vec!["I'm a result!"];
+		let results: Vec<&str> = vec!["I'm a result!"];
 		
 		// assert result
 		assert_eq!("1", count(&results));
-    }
+	}
 }
 
 pub fn help() {
 	println!("\
-Usage: minigrep [OPTION]... PATTERN [FILEPATH]
-Search for PATTERN in a FILE.
+Usage: minigrep [OPTION]... PATTERN [FILE]...
+Search for PATTERN in each FILE.
 Example: minigrep -i 'hello world' hello.txt
+With no FILE, or when FILE is -, read standard input.
+Short options can be bundled (-in), and an argument of -- ends the options.
+Defaults for any long option can go in a config file (MINIGREP_CONFIG, or
+~/.config/minigrep/config) and in MINIGREP_OPTS; the command line wins.
 
 Pattern selection and interpretation:
-  -i, --ignore-case         ignore case distinctions in patterns and data
+  -E, --regexp              PATTERN is a regular expression
+  -e, --pattern PATTERN     search for PATTERN; can be given many times,
+                            and then every other argument is a FILE
+  -f, --file FILE           take patterns from FILE, one per line
+  -i, --ignore-case         ignore case distinctions in patterns and data,
+                            folding case the Unicode way (so 'SS' and 'ß'
+                            match); with -E only letter for letter folding
+  -S, --smart-case          ignore case unless PATTERN has a capital letter
+      --fuzzy K             match anything within K edits of PATTERN, an
+                            edit being one character inserted, deleted or
+                            changed; with --verbose each line is prefixed
+                            with the fewest edits it took, as ~K
+  -w, --word-regexp         match only whole words
+  -x, --line-regexp         match only whole lines
+  -U, --multiline           let a match run over line breaks, so PATTERN
+                            can hold a newline (with -E, so can \\n); each
+                            match shows every line it covers, and -c
+                            counts matches rather than lines
+
+File and directory selection:
+  -r, --recursive           search every file under FILE when it is a
+                            directory, prefixing each line with its path
+  -j, --threads NUM         search up to NUM files at the same time
+  -F, --follow              once the end of FILE is reached, keep watching
+                            it for lines appended later, until Ctrl-C; FILE
+                            may be truncated or replaced, as logs rotate
+  -z, --search-zip          search inside compressed FILEs, told apart by
+                            their first bytes: gzip, and bzip2, xz or zstd
+                            if built with the cargo feature of that name
+      --include GLOB        when recursing, only search files whose name
+                            (or path, if GLOB has a '/') matches GLOB
+      --exclude GLOB        when recursing, skip files and directories
+                            matching GLOB; both can be given many times
+      --no-ignore           don't respect .gitignore and .minigrepignore
+                            files, or skip .git directories
+      --hidden              also search hidden files and directories
 
 Miscellaneous:
   -h, --help                display this help text and exit
 
 Output control:
-  -v, --verbose             include additional output
+  -v, --invert-match        select non-matching lines
+      --verbose             include additional output
   -c, --count               print only a count of selected lines per FILE
+  -l, --files-with-matches  print only the names of FILEs with a match
+  -L, --files-without-match print only the names of FILEs with no match
+  -m, --max-count NUM       stop reading a FILE after NUM selected lines
+  -q, --quiet, --silent     print nothing, stop at the first match; the
+                            exit status is 0 on a match, 1 on none
+  -n, --line-number         print line number with output lines
+  -b, --byte-offset         print the byte offset with output lines
+      --color[=WHEN]        highlight matches; WHEN is 'always', 'never',
+                            or 'auto' (the default, unless NO_COLOR is set)
+      --json                print results as JSON Lines, one event per line
+      --replace TEXT        print lines with every match replaced by TEXT;
+                            with -E, $1 or ${{name}} refer to capture groups
+      --in-place[=SUFFIX]   with --replace, edit each FILE instead of
+                            printing, keeping a backup at FILE+SUFFIX;
+                            binary files are left alone without -a
+      --binary-files=TYPE   how to treat files with a NUL byte near the
+                            start; TYPE is 'binary' (the default, only say
+                            \"Binary file FILE matches\"), 'text' or
+                            'without-match' (skip them)
+  -a, --text                same as --binary-files=text
+      --encoding LABEL      read FILEs as LABEL, such as 'utf-16le',
+                            'utf-16be' or 'latin1', printing them as UTF-8;
+                            'auto' (the default) only goes by a byte order
+                            mark, and a byte order mark always wins
+
+Context control:
+  -A, --after-context NUM   print NUM lines of trailing context
+  -B, --before-context NUM  print NUM lines of leading context
+  -C, --context NUM         print NUM lines of output context
 ");
 }
 
 pub fn verbose(config: &Config) {
-	println!("Searching for {}", config.query);
-    println!("In file {}", config.file_path);
+	// with --json stdout is JSON Lines and nothing else, so this goes to stderr
+	let mut out: Box<dyn Write> = if config.json {
+		Box::new(io::stderr().lock())
+	} else {
+		Box::new(io::stdout().lock())
+	};
+	// like println!, there's nowhere left to report a failure to write
+	write_verbose(config, &mut out).expect("failed printing --verbose settings");
+}
+
+fn write_verbose(config: &Config, out: &mut dyn Write) -> io::Result<()> {
+	for (source, settings) in &config.sources {
+		if !settings.is_empty() {
+			writeln!(out, "Settings from {source}: {}", settings.join(" "))?;
+		}
+	}
+	for pattern in &config.patterns {
+		writeln!(out, "Searching for {pattern}")?;
+	}
+	if let Some(max) = config.fuzzy {
+		writeln!(out, "Allowing up to {max} edit(s)")?;
+	}
+	if config.file_paths.is_empty() {
+		writeln!(out, "In standard input")?;
+	}
+	for file_path in &config.file_paths {
+		writeln!(out, "In file {file_path}")?;
+	}
+	Ok(())
 }
 
 pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
@@ -129,42 +717,553 @@ pub fn search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
 	 * that the lifetime of data from our document's contents will need 
 	 * to live on with our returned vector
 	 */
-	 contents.lines() // lines makes an iterator of contents
-			 .filter(|line| line.contains(query))
-			 .collect()
+	let finder = Finder::new(query, false); // the query is prepared just once
+	contents.lines() // lines makes an iterator of contents
+			.filter(|line| finder.is_match(line))
+			.collect()
 }
 
 pub fn search_case_insensitive<'a>(
 	query: &str, contents: &'a str) -> Vec<&'a str> {
 	
-	let query: String = query.to_lowercase();	
+	// no lowercase copy of each line, Finder folds case as it compares
+	let finder = Finder::new(query, true);
+	contents.lines()
+			.filter(|line| finder.is_match(line))
+			.collect()
+}
+
+pub fn search_fuzzy<'a>(
+	query: &str, contents: &'a str, max_distance: usize) -> Vec<&'a str> {
+	
+	// lines holding something at most max_distance edits away from query
+	let fuzzy = Fuzzy::new(query, max_distance, false, false);
 	contents.lines()
-			.filter(|line| line.to_lowercase().contains(&query))
+			.filter(|line| fuzzy.is_match(line.as_bytes()))
 			.collect()
 }
 
-pub fn count(results: & Vec<&str>) -> String {
+pub fn search_multiline<'a>(pattern: &Regex, contents: &'a str) -> Vec<&'a str> {
+	/* the pattern runs over all of contents rather than line by line, so a
+	 * match may take in line breaks. each match gives back every line it
+	 * touches, from the start of its first line to the end of its last.
+	 */
+	pattern.find_iter(contents)
+		.map(|found| {
+			let (start, end) = multiline::line_range(contents.as_bytes(), found.start(), found.end());
+			&contents[start..end]
+		})
+		.collect()
+}
+
+pub fn build_regex(query: &str, ignore_case: bool) -> Result<Regex, regex::Error> {
+	// compiling can fail on a bad pattern, so hand the error back to run
+	RegexBuilder::new(query)
+		.case_insensitive(ignore_case)
+		.build()
+}
+
+pub fn count<T>(results: &[T]) -> String {
 	// return vector containing string of length
 	format!("{}", results.len())
 }
 
-pub fn run(config: Config) -> Result<(), Box<dyn Error>> {
-    // ? to either unwrap or propogate an error by returning early
-    let contents = fs::read_to_string(config.file_path)?;
-    
-    let results = if config.ignore_case {
-		search_case_insensitive(&config.query, &contents)
+pub fn walk(path: &Path) -> Vec<PathBuf> {
+	walk_with(path, &Filter::everything())
+}
+
+pub fn walk_with(path: &Path, filter: &Filter) -> Vec<PathBuf> {
+	/* collect every regular file below path, depth first and sorted so the
+	 * output order doesn't depend on the filesystem. directories we can't
+	 * open are reported on stderr and skipped rather than ending the walk.
+	 */
+	let mut files = Vec::new();
+	
+	if !path.is_dir() {
+		files.push(path.to_path_buf());
+		return files;
+	}
+	
+	let mut ignores = IgnoreStack::new(path);
+	walk_dir(path, path, filter, &mut ignores, &mut files);
+	files
+}
+
+fn walk_dir(
+	root: &Path, dir: &Path, filter: &Filter,
+	ignores: &mut IgnoreStack, files: &mut Vec<PathBuf>
+) {
+	let mut entries: Vec<_> = match fs::read_dir(dir) {
+		Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+		Err(e) => {
+			eprintln!("minigrep: {}: {e}", dir.display());
+			return;
+		}
+	};
+	entries.sort_by_key(|entry| entry.file_name());
+	
+	// this directory's own ignore files only hold while we're inside it
+	let pushed = if filter.ignore_files { ignores.push_dir(dir) } else { 0 };
+	
+	for entry in entries {
+		let path = entry.path();
+		let hidden = entry.file_name().to_string_lossy().starts_with('.');
+		if hidden && !filter.hidden {
+			continue;
+		}
+		
+		let relative = ignore::to_slashes(path.strip_prefix(root).unwrap_or(&path));
+		
+		// file_type doesn't follow symlinks, so links can't loop us forever
+		match entry.file_type() {
+			Ok(kind) if kind.is_dir() => {
+				// .git is never worth searching while ignore files are on
+				let skip = filter.ignore_files
+					&& (entry.file_name() == ".git" || ignores.is_ignored(&path, true));
+				if !skip && !filter.excludes(&relative) {
+					walk_dir(root, &path, filter, ignores, files);
+				}
+			}
+			Ok(kind) if kind.is_file() => {
+				let skip = filter.ignore_files && ignores.is_ignored(&path, false);
+				if !skip && filter.wants_file(&relative) {
+					files.push(path);
+				}
+			}
+			_ => (),
+		}
+	}
+	
+	ignores.pop(pushed);
+}
+
+fn line_prefix(
+	config: &Config, name: Option<&str>, found: &ByteLine, sep: char
+) -> String {
+	/* grep's order: FILE:LINE:OFFSET: with each part only when asked for.
+	 * sep is ':' for a matching line and '-' for a context line.
+	 */
+	let colored = config.color == ColorChoice::Always;
+	let sep = color::paint(colored, color::SEPARATOR, &sep.to_string());
+	
+	let mut prefix = String::new();
+	if let Some(name) = name {
+		prefix.push_str(&color::paint(colored, color::FILE_NAME, name));
+		prefix.push_str(&sep);
+	}
+	if config.line_number {
+		let number = found.line_number.to_string();
+		prefix.push_str(&color::paint(colored, color::LINE_NUMBER, &number));
+		prefix.push_str(&sep);
+	}
+	if config.byte_offset {
+		let offset = found.byte_offset.to_string();
+		prefix.push_str(&color::paint(colored, color::BYTE_OFFSET, &offset));
+		prefix.push_str(&sep);
+	}
+	prefix
+}
+
+fn write_line(
+	out: &mut impl Write,
+	config: &Config,
+	matcher: &dyn Matcher,
+	name: Option<&str>,
+	line: &ByteLine,
+	sep: char,
+	spans: Option<&[(usize, usize)]>, // what to highlight, if already known
+) -> io::Result<()> {
+	// the line goes out as raw bytes, whatever encoding it happens to be in
+	out.write_all(line_prefix(config, name, line, sep).as_bytes())?;
+	
+	// only selected lines have anything to highlight or replace, -v lines never do
+	let selected = sep == ':' && !config.invert_match;
+	
+	// --fuzzy --verbose: how close the line came, as one more prefix field
+	if config.verbose && selected {
+		if let Some(distance) = matcher.distance(line.bytes) {
+			let colored = config.color == ColorChoice::Always;
+			write!(out, "~{distance}{}", color::paint(colored, color::SEPARATOR, ":"))?;
+		}
+	}
+	
+	if let Some(replacement) = config.replace.as_ref().filter(|_| selected) {
+		let (replaced, _) = matcher.replace_all(line.bytes, replacement.as_bytes(), config.regexp);
+		out.write_all(&replaced)?;
+		return out.write_all(b"\n");
+	}
+	
+	if config.color != ColorChoice::Always || !selected {
+		out.write_all(line.bytes)?;
+		return out.write_all(b"\n");
+	}
+	
+	let found;
+	let spans = match spans {
+		Some(spans) => spans,
+		None => {
+			found = matcher.find_all(line.bytes);
+			&found
+		}
+	};
+	
+	let mut written = 0;
+	for &(start, end) in spans {
+		out.write_all(&line.bytes[written..start])?;
+		write!(out, "\x1b[{}m", color::MATCH)?;
+		out.write_all(&line.bytes[start..end])?;
+		out.write_all(b"\x1b[0m")?;
+		written = end;
+	}
+	out.write_all(&line.bytes[written..])?;
+	out.write_all(b"\n")
+}
+
+// the Sink behind the command line, printing what's found the way grep does
+struct Printer<'a, W: Write> {
+	config: &'a Config,
+	matcher: &'a dyn Matcher,
+	out: W,
+	with_names: bool,    // whether each line starts with its file's name
+	path: String,        // the input being searched
+	printed_group: bool, // groups get a "--" between them
+}
+
+impl<'a, W: Write> Printer<'a, W> {
+	fn new(config: &'a Config, matcher: &'a dyn Matcher, out: W, with_names: bool) -> Printer<'a, W> {
+		Printer { config, matcher, out, with_names, path: String::new(), printed_group: false }
+	}
+	
+	fn listing(&self) -> bool {
+		// -l, -L and -q only want to know if there's a match, not what it is
+		self.config.quiet || self.config.files_with_matches || self.config.files_without_match
+	}
+}
+
+impl<W: Write> Sink for Printer<'_, W> {
+	fn on_begin_file(&mut self, path: &str) -> io::Result<()> {
+		path.clone_into(&mut self.path);
+		
+		// JSON always names the input, even when there's only one
+		if self.config.json && !self.listing() {
+			writeln!(self.out, "{}", json::begin(path))?;
+		}
+		Ok(())
+	}
+	
+	fn on_match(&mut self, found: &SinkMatch) -> io::Result<()> {
+		let config = self.config;
+		if self.listing() || (config.count && !config.json) {
+			return Ok(());
+		}
+		
+		if config.json {
+			return writeln!(self.out, "{}", json::line("match", &self.path, &found.line, &found.spans()));
+		}
+		
+		// the spans are only worth looking for when they'll be colored
+		let spans = (config.color == ColorChoice::Always).then(|| found.spans());
+		let name = self.with_names.then_some(self.path.as_str());
+		write_line(&mut self.out, config, self.matcher, name, &found.line, ':', spans.as_deref())
+	}
+	
+	fn on_context(&mut self, line: &ByteLine) -> io::Result<()> {
+		let config = self.config;
+		if self.listing() || (config.count && !config.json) {
+			return Ok(());
+		}
+		
+		if config.json {
+			return writeln!(self.out, "{}", json::line("context", &self.path, line, &[]));
+		}
+		
+		let name = self.with_names.then_some(self.path.as_str());
+		write_line(&mut self.out, config, self.matcher, name, line, '-', None)
+	}
+	
+	fn on_group_start(&mut self) -> io::Result<()> {
+		if self.listing() || self.config.count || self.config.json {
+			return Ok(());
+		}
+		
+		if self.printed_group {
+			let colored = self.config.color == ColorChoice::Always;
+			writeln!(self.out, "{}", color::paint(colored, color::SEPARATOR, "--"))?;
+		}
+		self.printed_group = true;
+		Ok(())
+	}
+	
+	fn on_finish(&mut self, finish: &Finish) -> io::Result<()> {
+		let config = self.config;
+		let colored = config.color == ColorChoice::Always;
+		let matched = finish.matched_lines > 0;
+		
+		if config.quiet {
+			// not a word, the exit status says it all
+		} else if config.files_with_matches || config.files_without_match {
+			// -l lists the files that matched, -L the ones that didn't
+			if matched == config.files_with_matches {
+				writeln!(self.out, "{}", color::paint(colored, color::FILE_NAME, finish.path))?;
+			}
+		} else if finish.binary && !config.count && !config.json {
+			// counts and JSON have no raw lines to spoil, so they carry on as usual
+			if matched {
+				writeln!(self.out, "Binary file {} matches", finish.path)?;
+			}
+		} else if config.json {
+			writeln!(self.out, "{}", json::end(finish.path, finish.matched_lines))?;
+		} else if config.count {
+			// with -U that's matches, unless -v is picking lines
+			let shown = finish.matches;
+			match self.with_names.then_some(finish.path) {
+				Some(name) => writeln!(
+					self.out, "{}{}{shown}",
+					color::paint(colored, color::FILE_NAME, name),
+					color::paint(colored, color::SEPARATOR, ":"),
+				)?,
+				None => writeln!(self.out, "{shown}")?,
+			}
+		}
+		
+		Ok(())
+	}
+}
+
+fn searcher_for<'m>(config: &Config, matcher: &'m dyn Matcher) -> Searcher<'m> {
+	let mut searcher = Searcher::new(matcher);
+	searcher.invert_match = config.invert_match;
+	searcher.multiline = config.multiline;
+	searcher.search_zip = config.search_zip;
+	searcher.encoding = config.encoding;
+	// counts and JSON have no raw lines to spoil, so binary files are just text
+	searcher.binary_files = match config.binary_files {
+		BinaryFiles::Binary if config.count || config.json => BinaryFiles::Text,
+		policy => policy,
+	};
+	
+	// when one line settles it, the search can stop at the first match
+	if config.quiet || config.files_with_matches || config.files_without_match {
+		searcher.max_count = Some(1);
+	} else {
+		searcher.max_count = config.max_count;
+		searcher.before_context = config.before_context;
+		searcher.after_context = config.after_context;
+	}
+	searcher
+}
+
+fn search_input<S: Sink + ?Sized>(
+	config: &Config, searcher: &Searcher, input: &Input, sink: &mut S
+) -> Result<usize, SearchError> {
+	let opened = if config.follow { input.follow() } else { input.open() };
+	let reader = opened.map_err(SearchError::Input)?;
+	searcher.search_reader(&input.name(), reader, sink)
+}
+
+// running totals over every input, for the JSON summary
+#[derive(Default)]
+struct Totals {
+	searched: usize,
+	files_with_matches: usize,
+	matched_lines: usize,
+	failed: usize,
+}
+
+fn check_searched(
+	input: &Input, result: Result<usize, SearchError>, totals: &mut Totals
+) -> io::Result<()> {
+	match result {
+		Ok(count) => {
+			totals.searched += 1;
+			totals.matched_lines += count;
+			if count > 0 {
+				totals.files_with_matches += 1;
+			}
+			Ok(())
+		}
+		// one unreadable file shouldn't abort the rest of the search
+		Err(SearchError::Input(e)) => {
+			eprintln!("minigrep: {}: {e}", input.name());
+			totals.failed += 1;
+			Ok(())
+		}
+		Err(SearchError::Output(e)) => Err(e),
+	}
+}
+
+fn edit_inputs(config: &Config, matcher: &dyn Matcher, inputs: &[Input]) -> Result<bool, Box<dyn Error>> {
+	// Config makes sure --in-place never comes without --replace
+	let replacement = config.replace.as_deref().unwrap_or_default();
+	let mut failed = 0;
+	let mut replaced = false;
+	
+	for input in inputs {
+		let edited = match input {
+			Input::Stdin => Err(io::Error::other("standard input can't be edited in place")),
+			Input::File(path) => replace::edit_in_place(
+				path, matcher, replacement.as_bytes(), config.regexp,
+				config.backup_suffix.as_deref(), config.binary_files
+			),
+		};
+		
+		replaced |= edited.as_ref().is_ok_and(|&count| count.is_some_and(|count| count > 0));
+		match edited {
+			// rewriting a line at a time could break a binary file, only -a says it isn't one
+			Ok(None) => eprintln!("minigrep: {}: binary file left alone, -a edits it", input.name()),
+			Ok(Some(1)) => println!("{}: 1 replacement", input.name()),
+			Ok(Some(count)) => println!("{}: {count} replacements", input.name()),
+			// the file is untouched, carry on with the others
+			Err(e) => {
+				eprintln!("minigrep: {}: {e}", input.name());
+				failed += 1;
+			}
+		}
+	}
+	
+	if failed > 0 {
+		return Err(format!("{failed} file(s) could not be edited").into());
+	}
+	
+	Ok(replaced)
+}
+
+pub fn run(mut config: Config) -> Result<bool, Box<dyn Error>> {
+	/* returns whether anything was selected: a matching line, or a file
+	 * listed by -L. main turns that into grep's exit status.
+	 */
+	// settle "auto" once, against the real stdout, before any threads start
+	config.color = config.color.resolve();
+	
+	// compile the pattern first so a typo fails before any file is read
+	let matcher = PatternMatcher::new(&config)?;
+	
+	// no FILE operands means read standard input, like `cat` does
+	let operands = if config.file_paths.is_empty() {
+		vec![String::from("-")]
+	} else {
+		config.file_paths.clone()
+	};
+	
+	// what a recursive walk leaves out, files named outright are always searched
+	let filter = Filter {
+		ignore_files: !config.no_ignore,
+		hidden: config.hidden,
+		include: config.include.iter().map(|glob| Glob::new(glob)).collect(),
+		exclude: config.exclude.iter().map(|glob| Glob::new(glob)).collect(),
+	};
+	
+	let mut inputs = Vec::new();
+	for operand in &operands {
+		if operand == "-" {
+			inputs.push(Input::Stdin);
+		} else if config.recursive {
+			inputs.extend(walk_with(Path::new(operand), &filter).into_iter().map(Input::File));
+		} else {
+			inputs.push(Input::File(PathBuf::from(operand)));
+		}
+	}
+	
+	if config.in_place {
+		return edit_inputs(&config, &matcher, &inputs);
+	}
+	
+	if config.follow {
+		/* Ctrl-C ends following the way the end of a file would, so counts
+		 * and summaries still come out. a second one doesn't wait for that.
+		 */
+		ctrlc::set_handler(|| {
+			if follow::INTERRUPTED.swap(true, Ordering::Relaxed) {
+				process::exit(130);
+			}
+		})?;
+	}
+	
+	// only name the file on each line when there's more than one to tell apart
+	let with_names = config.recursive || operands.len() > 1;
+	let searcher = searcher_for(&config, &matcher);
+	let mut printer = Printer::new(&config, &matcher, io::stdout().lock(), with_names);
+	let mut totals = Totals::default();
+	
+	let stopped = if config.threads > 1 && inputs.len() > 1 {
+		let mut stopped = None;
+		
+		parallel::search_in_parallel(
+			&config, &searcher, &inputs, with_names, config.threads,
+			|input, output, grouped, result| {
+				// the worker couldn't know if an earlier file printed a group
+				let written = if grouped && printer.printed_group {
+					let colored = config.color == ColorChoice::Always;
+					writeln!(printer.out, "{}", color::paint(colored, color::SEPARATOR, "--"))
+				} else {
+					Ok(())
+				};
+				printer.printed_group |= grouped;
+				
+				let result = written
+					.and_then(|()| printer.out.write_all(&output))
+					.map_err(SearchError::Output)
+					.and(result);
+				
+				match check_searched(input, result, &mut totals) {
+					// -q is done as soon as anything matches
+					Ok(()) => !(config.quiet && totals.matched_lines > 0),
+					Err(e) => {
+						stopped = Some(e);
+						false
+					}
+				}
+			},
+		);
+		
+		stopped
 	} else {
-		search(&config.query, &contents)
+		let mut stopped = None;
+		for input in &inputs {
+			let result = search_input(&config, &searcher, input, &mut printer);
+			
+			if let Err(e) = check_searched(input, result, &mut totals) {
+				stopped = Some(e);
+				break;
+			}
+			if config.quiet && totals.matched_lines > 0 {
+				break;
+			}
+		}
+		stopped
 	};
 	
-	if let true = config.count {
-		println!("{}", count(&results));
+	// with -q a match is all that matters, even if some file was unreadable
+	if config.quiet && totals.matched_lines > 0 {
+		return Ok(true);
+	}
+	let selected = if config.files_without_match {
+		totals.files_with_matches < totals.searched
 	} else {
-		for line in results {
-			println!("{line}");
+		totals.matched_lines > 0
+	};
+	
+	let stopped = stopped.or_else(|| {
+		if !config.json {
+			return None;
 		}
+		let summary = json::summary(
+			totals.searched, totals.files_with_matches, totals.matched_lines
+		);
+		writeln!(printer.out, "{summary}").err()
+	});
+	
+	if let Some(e) = stopped {
+		// whoever was reading went away (`| head`), no need to complain
+		if e.kind() == io::ErrorKind::BrokenPipe {
+			return Ok(selected);
+		}
+		return Err(e.into());
 	}
-    
-    Ok(())
+	
+	if totals.failed > 0 {
+		return Err(format!("{} file(s) could not be read", totals.failed).into());
+	}
+	
+	Ok(selected)
 }
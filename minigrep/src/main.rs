@@ -9,34 +9,34 @@ use minigrep::Config;
  */
 
 fn main() {
-    // create args vector, using collect method to return iterator
-    // collect needs type annotationlet args = env::args(); 
-    let n = env::args().count(); // moves out env::args(), returns usize
-    
-    // call eng::args() anew. check if this is a help request
-    // call to next_back() consumes an element
-    // unwrap is guaranteed an element in this case
-    let check_help = env::args().next_back().unwrap();
-    
-    if let "-h" | "--help" = check_help.as_str() { 
-        minigrep::help();
-        process::exit(0);
-    }
-    
-    let config = Config::new(env::args(), n).unwrap_or_else(|err| {
-        // unwrap_or_else is a Result method, this is its closure
-        eprintln!("Problem parsing arguments: {err}");
-        // exiting the command line tool with a nonzero error code 
-        process::exit(1);
-    });
-    
-    if config.verbose {
-        minigrep::verbose(&config);
-    }
-    
-    if let Err(e) = minigrep::run(config) {
-        // print to std error and exit
-        eprintln!("Application error: {e}");
-        process::exit(1);
-    }
+	// env::args() gives an iterator over the arguments, program name first
+	let config = Config::new(env::args()).unwrap_or_else(|err| {
+		// unwrap_or_else is a Result method, this is its closure
+		eprintln!("Problem parsing arguments: {err}");
+		eprintln!("Try 'minigrep --help' for more information.");
+		// exiting the command line tool with a nonzero error code,
+		// 2 like grep, since 1 only means nothing matched
+		process::exit(2);
+	});
+	
+	// -h can show up anywhere, the parser just makes a note of it
+	if config.help {
+		minigrep::help();
+		process::exit(0);
+	}
+	
+	if config.verbose {
+		minigrep::verbose(&config);
+	}
+	
+	// grep's exit status: 0 if something matched, 1 if not, 2 on trouble
+	match minigrep::run(config) {
+		Ok(true) => (),
+		Ok(false) => process::exit(1),
+		Err(e) => {
+			// print to std error and exit
+			eprintln!("Application error: {e}");
+			process::exit(2);
+		}
+	}
 }
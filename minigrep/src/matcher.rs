@@ -0,0 +1,555 @@
+use std::error::Error;
+
+use aho_corasick::{AhoCorasick, MatchKind};
+use regex::bytes::{Regex, RegexBuilder};
+
+use crate::fold::{self, FoldedLine};
+use crate::fuzzy::Fuzzy;
+use crate::Config;
+
+enum Engine {
+	Regex(Regex),
+	// a set of plain strings, all looked for in a single pass over the line
+	Literals(AhoCorasick),
+	// the same, but the strings are case folded and so is each line
+	Folded { automaton: AhoCorasick, whole_line: bool },
+	// within so many edits of any of the patterns, for --fuzzy
+	Fuzzy(Vec<Fuzzy>),
+}
+
+/* anything that can find matches in a line of bytes. a Searcher only needs
+ * find_at, the rest have sensible defaults built on it, which a Matcher can
+ * do better when it knows more. Sync so one can be shared between threads.
+ */
+pub trait Matcher: Sync {
+	// the first match at or after start, as (start, end) byte offsets
+	fn find_at(&self, line: &[u8], start: usize) -> Option<(usize, usize)>;
+	
+	fn is_match(&self, line: &[u8]) -> bool {
+		self.find_at(line, 0).is_some()
+	}
+	
+	fn find_all(&self, line: &[u8]) -> Vec<(usize, usize)> {
+		// every non-overlapping match, skipping empty ones as nothing to show
+		self.find_every(line).into_iter().filter(|&(s, e)| e > s).collect()
+	}
+	
+	fn find_every(&self, text: &[u8]) -> Vec<(usize, usize)> {
+		/* every non-overlapping match, empty ones included, as -U counts
+		 * them. an empty match right where the last one ended isn't new.
+		 */
+		let mut found = Vec::new();
+		let mut start = 0;
+		let mut last_end = None;
+		
+		while start <= text.len() {
+			let Some((s, e)) = self.find_at(text, start) else {
+				break;
+			};
+			if e > s || last_end != Some(e) {
+				found.push((s, e));
+			}
+			last_end = Some(e);
+			start = if e > s { e } else { e + 1 };
+		}
+		
+		found
+	}
+	
+	fn replace_all(&self, line: &[u8], replacement: &[u8], _expand: bool) -> (Vec<u8>, usize) {
+		/* swap every match for replacement, returning the new line and how
+		 * many replacements were made. only a regex has groups for expand
+		 * to fill in, so by default the replacement is just text.
+		 */
+		splice(line, &self.find_every(line), |_, out| out.extend_from_slice(replacement))
+	}
+	
+	// how many edits the best approximate match took, for --fuzzy
+	fn distance(&self, _line: &[u8]) -> Option<usize> {
+		None
+	}
+}
+
+// the Matcher built from a Config's PATTERNs and options
+pub struct PatternMatcher {
+	engine: Engine,
+	word: bool,
+}
+
+impl PatternMatcher {
+	pub fn new(config: &Config) -> Result<PatternMatcher, Box<dyn Error>> {
+		/* a plain query is escaped so one regex engine can serve both modes,
+		 * which also gives us exact match spans for free. it works on bytes
+		 * rather than str so that lines which aren't valid UTF-8 still match.
+		 * several plain strings go to an Aho-Corasick automaton instead, so
+		 * a long list costs one pass over each line rather than one per
+		 * pattern.
+		 *
+		 * ignoring case on plain strings uses full Unicode case folding, so
+		 * that "strasse" finds "Straße", which means folding each line too.
+		 * regex only knows simple folding, one character to one character,
+		 * so that's what -E gets, as no folding of ours can look inside a
+		 * regular expression.
+		 *
+		 * --fuzzy needs its own search altogether, as any other engine only
+		 * knows exact matches.
+		 */
+		let patterns = &config.patterns;
+		
+		let engine = if let Some(max) = config.fuzzy {
+			Engine::Fuzzy(patterns.iter()
+				.map(|p| Fuzzy::new(p, max, config.ignore_case, config.line_regexp))
+				.collect())
+		} else if config.ignore_case && !config.regexp {
+			let folded: Vec<String> = patterns.iter().map(|p| fold::fold(p)).collect();
+			Engine::Folded {
+				automaton: AhoCorasick::builder()
+					// for -x, any pattern that covers the line has to win
+					.match_kind(if config.line_regexp {
+						MatchKind::LeftmostLongest
+					} else {
+						MatchKind::LeftmostFirst
+					})
+					// ASCII lines aren't folded, so let the automaton do it
+					.ascii_case_insensitive(true)
+					.build(&folded)?,
+				whole_line: config.line_regexp,
+			}
+		} else if (patterns.len() != 1 && !config.regexp && !config.line_regexp)
+			// no patterns at all can only ever come from an empty -f file
+			|| patterns.is_empty()
+		{
+			Engine::Literals(
+				AhoCorasick::builder()
+					// like regex alternation, the earliest pattern wins a tie
+					.match_kind(MatchKind::LeftmostFirst)
+					.build(patterns)?
+			)
+		} else {
+			let alternatives: Vec<String> = patterns.iter()
+				.map(|p| if config.regexp { p.clone() } else { regex::escape(p) })
+				.collect();
+			let mut pattern = if alternatives.len() == 1 {
+				alternatives[0].clone()
+			} else {
+				format!("(?:{})", alternatives.join(")|(?:"))
+			};
+			
+			// -x: the whole line has to be the match, not just part of it
+			if config.line_regexp {
+				pattern = format!("^(?:{pattern})$");
+			}
+			
+			Engine::Regex(
+				RegexBuilder::new(&pattern)
+					.case_insensitive(config.ignore_case)
+					// with -U a search sees many lines, ^ and $ still mean one
+					.multi_line(config.multiline)
+					.build()?
+			)
+		};
+		
+		Ok(PatternMatcher {
+			engine,
+			word: config.word_regexp,
+		})
+	}
+	
+	fn fold(&self, line: &[u8]) -> Option<FoldedLine> {
+		// an ASCII line folds to itself, give or take ASCII case
+		match self.engine {
+			Engine::Folded { .. } if !line.is_ascii() => Some(FoldedLine::new(line)),
+			_ => None,
+		}
+	}
+	
+	fn find_from(
+		&self, line: &[u8], folded: Option<&FoldedLine>, start: usize
+	) -> Option<(usize, usize)> {
+		match &self.engine {
+			Engine::Regex(pattern) => {
+				pattern.find_at(line, start).map(|found| (found.start(), found.end()))
+			}
+			Engine::Literals(automaton) => {
+				let input = aho_corasick::Input::new(line).range(start..);
+				automaton.find(input).map(|found| (found.start(), found.end()))
+			}
+			Engine::Folded { automaton, whole_line } => {
+				let (s, e) = match folded {
+					None => {
+						let input = aho_corasick::Input::new(line).range(start..);
+						let found = automaton.find(input)?;
+						(found.start(), found.end())
+					}
+					Some(folded) => {
+						let from = folded.to_folded(start);
+						let input = aho_corasick::Input::new(&folded.bytes).range(from..);
+						let found = automaton.find(input)?;
+						folded.to_original(found.start(), found.end())
+					}
+				};
+				
+				// the anchors the regex engine would have had for -x
+				(!whole_line || (s, e) == (0, line.len())).then_some((s, e))
+			}
+			Engine::Fuzzy(fuzzies) => {
+				// the earliest of them, and the first listed if they tie
+				fuzzies.iter()
+					.filter_map(|fuzzy| fuzzy.find_at(line, start))
+					.min_by_key(|&(s, _)| s)
+			}
+		}
+	}
+	
+	fn find_folded(
+		&self, line: &[u8], folded: Option<&FoldedLine>, start: usize
+	) -> Option<(usize, usize)> {
+		/* the first match at or after start as (start, end) byte offsets.
+		 * with -w a match only counts when it isn't glued to other word
+		 * characters, so keep trying one character further along.
+		 */
+		let mut start = start;
+		
+		while start <= line.len() {
+			let (s, e) = self.find_from(line, folded, start)?;
+			
+			if !self.word || is_word_bounded(line, s, e) {
+				return Some((s, e));
+			}
+			
+			start = s + 1;
+		}
+		
+		None
+	}
+}
+
+impl Matcher for PatternMatcher {
+	fn find_at(&self, line: &[u8], start: usize) -> Option<(usize, usize)> {
+		self.find_folded(line, self.fold(line).as_ref(), start)
+	}
+	
+	fn find_every(&self, text: &[u8]) -> Vec<(usize, usize)> {
+		// the same as the default, but folding the text once, not every time
+		let mut found = Vec::new();
+		let mut start = 0;
+		let mut last_end = None;
+		let folded = self.fold(text);
+		
+		while let Some((s, e)) = self.find_folded(text, folded.as_ref(), start) {
+			if e > s || last_end != Some(e) {
+				found.push((s, e));
+			}
+			last_end = Some(e);
+			start = if e > s { e } else { e + 1 };
+		}
+		
+		found
+	}
+	
+	fn replace_all(&self, line: &[u8], replacement: &[u8], expand: bool) -> (Vec<u8>, usize) {
+		// with expand, $1 or ${name} in the replacement are capture groups
+		let spans = self.find_every(line);
+		match &self.engine {
+			// captures_at finds each match again, groups and all
+			Engine::Regex(pattern) if expand => splice(line, &spans, |s, out| {
+				match pattern.captures_at(line, s) {
+					Some(groups) => groups.expand(replacement, out),
+					None => out.extend_from_slice(replacement),
+				}
+			}),
+			_ => splice(line, &spans, |_, out| out.extend_from_slice(replacement)),
+		}
+	}
+	
+	fn distance(&self, line: &[u8]) -> Option<usize> {
+		// how many edits the best fuzzy match took, None when not fuzzy
+		match &self.engine {
+			Engine::Fuzzy(fuzzies) => {
+				fuzzies.iter().filter_map(|fuzzy| fuzzy.distance(line)).min()
+			}
+			_ => None,
+		}
+	}
+}
+
+fn splice(
+	line: &[u8], spans: &[(usize, usize)], mut put: impl FnMut(usize, &mut Vec<u8>)
+) -> (Vec<u8>, usize) {
+	// line with put() writing something new in place of each span
+	let mut replaced = Vec::with_capacity(line.len());
+	let mut written = 0;
+	
+	for &(s, e) in spans {
+		replaced.extend_from_slice(&line[written..s]);
+		put(s, &mut replaced);
+		written = e;
+	}
+	
+	replaced.extend_from_slice(&line[written..]);
+	(replaced, spans.len())
+}
+
+fn is_word_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_'
+}
+
+fn char_before(line: &[u8], i: usize) -> Option<char> {
+	// a UTF-8 char is at most 4 bytes, the shortest valid tail is the char
+	(1..=i.min(4))
+		.find_map(|len| std::str::from_utf8(&line[i - len..i]).ok())
+		.and_then(|s| s.chars().next_back())
+}
+
+fn char_after(line: &[u8], i: usize) -> Option<char> {
+	(1..=(line.len() - i).min(4))
+		.find_map(|len| std::str::from_utf8(&line[i..i + len]).ok())
+		.and_then(|s| s.chars().next())
+}
+
+fn is_word_bounded(line: &[u8], start: usize, end: usize) -> bool {
+	let before = char_before(line, start);
+	let after = char_after(line, end);
+	
+	!before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::search_positions;
+	use crate::{searcher_for, Printer};
+	
+	const CONTENTS: &str = "\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.
+rust";
+	
+	fn config(flags: &[&str], query: &str) -> Config {
+		let mut args = vec!["minigrep"];
+		args.extend(flags);
+		args.push(query);
+		Config::from_args(args.iter().map(|arg| arg.to_string())).unwrap()
+	}
+	
+	fn matched<'a>(flags: &[&str], query: &str) -> Vec<&'a str> {
+		let config = config(flags, query);
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		// -v is the Searcher's business, a Matcher only finds matches
+		search_positions(CONTENTS, |line| matcher.is_match(line.as_bytes()) != config.invert_match)
+			.into_iter()
+			.map(|found| found.line)
+			.collect()
+	}
+	
+	#[test]
+	fn invert() {
+		assert_eq!(vec!["safe, fast, productive.", "Pick three.", "Trust me.", "rust"],
+			matched(&["-v"], "Rust"));
+		assert_eq!(vec!["safe, fast, productive.", "Pick three."],
+			matched(&["-v", "-i"], "rust"));
+	}
+	
+	#[test]
+	fn word() {
+		assert_eq!(vec!["Rust:"], matched(&["-w"], "Rust"));
+		assert_eq!(vec!["Rust:", "rust"], matched(&["-w", "-i"], "RUST"));
+		// "rust" inside "Trust" isn't a word of its own
+		assert_eq!(vec!["rust"], matched(&["-w"], "rust"));
+		assert_eq!(Vec::<&str>::new(), matched(&["-w"], "fas"));
+	}
+	
+	#[test]
+	fn word_retries_later_matches() {
+		let config = Config::from_args(
+			["minigrep", "-w", "ab"].iter().map(|arg| arg.to_string())
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		assert_eq!(Some((5, 7)), matcher.find_at(b"abab ab", 0));
+		assert!(!matcher.is_match(b"abab_ab"));
+		// word characters outside ASCII count too
+		assert!(!matcher.is_match("ab\u{e9}".as_bytes()));
+		assert!(matcher.is_match("\u{e9} ab \u{e9}".as_bytes()));
+	}
+	
+	#[test]
+	fn find_all_spans() {
+		let config = Config::from_args(
+			["minigrep", "-E", "o+|x*"].iter().map(|arg| arg.to_string())
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		assert_eq!(vec![(1, 3), (5, 6)], matcher.find_all(b"foo bo"));
+		assert!(matcher.find_all(b"abc").is_empty());
+	}
+	
+	#[test]
+	fn replace_literal() {
+		let config = Config::from_args(
+			["minigrep", "-i", "$1"].iter().map(|arg| arg.to_string())
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		// outside of regex mode a '$' is just a dollar sign
+		assert_eq!((b"x $2 y $2".to_vec(), 2), matcher.replace_all(b"x $1 y $1", b"$2", false));
+	}
+	
+	#[test]
+	fn replace_with_captures() {
+		let config = Config::from_args(
+			["minigrep", "-E", r"(\w+)@(\w+)"].iter().map(|arg| arg.to_string())
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		assert_eq!(
+			(b"mail example at user, other at them".to_vec(), 2),
+			matcher.replace_all(b"mail user@example, them@other", b"$2 at $1", true)
+		);
+	}
+	
+	#[test]
+	fn replace_empty_matches() {
+		let config = Config::from_args(
+			["minigrep", "-E", "x*"].iter().map(|arg| arg.to_string())
+		).unwrap();
+		let matcher = PatternMatcher::new(&config).unwrap();
+		
+		assert_eq!((b"-a-b-".to_vec(), 3), matcher.replace_all(b"axb", b"-", true));
+		assert_eq!((b"-".to_vec(), 1), matcher.replace_all(b"", b"-", true));
+	}
+	
+	fn matcher(args: &[&str]) -> PatternMatcher {
+		let mut all = vec!["minigrep"];
+		all.extend(args);
+		PatternMatcher::new(&Config::from_args(all.iter().map(|arg| arg.to_string())).unwrap()).unwrap()
+	}
+	
+	#[test]
+	fn any_of_several_literals() {
+		let matcher = matcher(&["-e", "three", "-e", "me", "-e", "x.y"]);
+		assert!(matches!(matcher.engine, Engine::Literals(_)));
+		
+		let lines: Vec<&str> = CONTENTS.lines()
+			.filter(|line| matcher.is_match(line.as_bytes()))
+			.collect();
+		assert_eq!(vec!["Pick three.", "Trust me."], lines);
+		
+		// regex metacharacters in a literal are only themselves
+		assert!(matcher.is_match(b"x.y"));
+		assert!(!matcher.is_match(b"xzy"));
+		
+		// the same leftmost, first-listed-wins spans the regex engine gives
+		let matcher = self::matcher(&["-e", "ab", "-e", "abc", "-e", "b"]);
+		assert_eq!(vec![(0, 2), (4, 5)], matcher.find_all(b"abc b"));
+	}
+	
+	#[test]
+	fn several_literals_with_flags() {
+		let matcher = matcher(&["-i", "-w", "-e", "RUST", "-e", "PICK"]);
+		assert!(matches!(matcher.engine, Engine::Folded { .. }));
+		assert!(matcher.is_match(b"Rust:"));
+		assert!(matcher.is_match(b"pick three"));
+		assert!(!matcher.is_match(b"Trust me."));
+		
+		// case folding isn't only for ASCII
+		let matcher = self::matcher(&["-i", "-e", "\u{c9}T\u{c9}", "-e", "hiver"]);
+		assert!(matcher.is_match("en \u{e9}t\u{e9}".as_bytes()));
+		assert!(matcher.is_match("HIVER".as_bytes()));
+		
+		// so does -x, which has to anchor each one
+		let matcher = self::matcher(&["-x", "-e", "rust", "-e", "Rust:"]);
+		assert!(matcher.is_match(b"Rust:"));
+		assert!(!matcher.is_match(b"Trust"));
+		
+		let matcher = self::matcher(&["--replace=X", "-e", "fast", "-e", "safe"]);
+		assert_eq!((b"X, X, productive.".to_vec(), 2),
+			matcher.replace_all(b"safe, fast, productive.", b"X", false));
+	}
+	
+	#[test]
+	fn several_regexes() {
+		let matcher = matcher(&["-E", "-e", "^P", "-e", "e\\.$"]);
+		let lines: Vec<&str> = CONTENTS.lines()
+			.filter(|line| matcher.is_match(line.as_bytes()))
+			.collect();
+		assert_eq!(vec!["safe, fast, productive.", "Pick three.", "Trust me."], lines);
+	}
+	
+	#[test]
+	fn no_patterns_match_nothing() {
+		let matcher = matcher(&["-f", "/dev/null"]);
+		assert!(!matcher.is_match(b"anything"));
+		assert!(!matcher.is_match(b""));
+		
+		// so inverted, that's everything
+		assert_eq!(5, matched(&["-v", "-x", "-f", "/dev/null"], "FILE").len());
+	}
+	
+	#[test]
+	fn folded_spans_are_original_spans() {
+		let matcher = matcher(&["-i", "STRASSE"]);
+		let line = "die Stra\u{df}e, die STRASSE".as_bytes();
+		assert_eq!(vec![(4, 11), (17, 24)], matcher.find_all(line));
+		
+		// the ß only has to match as a whole when a match ends inside it
+		let matcher = self::matcher(&["-i", "-e", "stras"]);
+		assert_eq!(vec![(0, 6)], matcher.find_all("Stra\u{df}e".as_bytes()));
+		
+		let matcher = self::matcher(&["-i", "--replace=road", "stra\u{df}e"]);
+		assert_eq!((b"one road, another road".to_vec(), 2),
+			matcher.replace_all("one STRASSE, another Stra\u{df}e".as_bytes(), b"road", false));
+	}
+	
+	#[test]
+	fn folded_word_and_line() {
+		let matcher = matcher(&["-i", "-w", "\u{3c3}\u{3bf}\u{3c2}"]);
+		assert!(matcher.is_match("\u{3a3}\u{39f}\u{3a3} \u{3b5}\u{3af}\u{3bd}\u{3b1}\u{3b9}".as_bytes()));
+		assert!(!matcher.is_match("\u{3c3}\u{3bf}\u{3c3}\u{3b1}".as_bytes()));
+		
+		let matcher = self::matcher(&["-i", "-x", "-e", "stra", "-e", "stra\u{df}e"]);
+		assert!(matcher.is_match("STRASSE".as_bytes()));
+		assert!(matcher.is_match("Stra".as_bytes()));
+		assert!(!matcher.is_match("Strasse!".as_bytes()));
+	}
+	
+	#[test]
+	fn line() {
+		assert_eq!(vec!["rust"], matched(&["-x"], "rust"));
+		assert_eq!(vec!["Rust:"], matched(&["-x", "-E"], "[A-Z]\\w+:"));
+		assert_eq!(vec!["rust"], matched(&["-x", "-i"], "RUST"));
+		assert_eq!(Vec::<&str>::new(), matched(&["-x"], "Rust"));
+	}
+	
+	#[test]
+	fn combined() {
+		assert_eq!(vec!["Rust:", "safe, fast, productive.", "Pick three.", "Trust me."],
+			matched(&["-v", "-x", "-i"], "RUST"));
+		assert_eq!(vec!["safe, fast, productive.", "Pick three.", "Trust me."],
+			matched(&["-v", "-w", "-i"], "rust"));
+		assert_eq!(vec!["Trust me."], matched(&["-w", "-x", "-E"], "T.* me\\."));
+	}
+	
+	#[test]
+	fn combined_count() {
+		// through the Searcher and Printer, so it's -c's own count that's checked
+		fn printed(flags: &[&str], query: &str) -> String {
+			let config = config(flags, query);
+			let matcher = PatternMatcher::new(&config).unwrap();
+			let mut out = Vec::new();
+			let mut printer = Printer::new(&config, &matcher, &mut out, false);
+			searcher_for(&config, &matcher)
+				.search_reader("contents", CONTENTS.as_bytes(), &mut printer)
+				.unwrap();
+			String::from_utf8(out).unwrap()
+		}
+		
+		assert_eq!("2\n", printed(&["-c", "-v", "-i"], "rust"));
+		assert_eq!("2\n", printed(&["-c", "-w", "-i"], "rust"));
+		assert_eq!("1\n", printed(&["-c", "-x", "-i"], "rust:"));
+		assert_eq!("4\n", printed(&["-c", "-v", "-x"], "rust"));
+	}
+}
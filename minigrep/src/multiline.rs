@@ -0,0 +1,108 @@
+/*
+ * -U: searching the whole input at once, so a match can run over a line
+ * break. Output still goes line by line, so what comes out of a search here
+ * is, for every line, whether some match covers any of it and which parts
+ * of it to highlight. Those lines can then be streamed like any others.
+ */
+
+use crate::stream::trim_terminator;
+
+pub struct Covered {
+	pub lines: Vec<bool>,                   // whether a match touches each line
+	pub starts: Vec<usize>,                 // how many matches begin on each line
+	pub spans: Vec<Vec<(usize, usize)>>,    // the matched parts of each line
+}
+
+pub fn line_starts(buffer: &[u8]) -> Vec<usize> {
+	// where every line begins, and a final '\n' doesn't begin another one
+	let mut starts = vec![0];
+	starts.extend(
+		buffer.iter()
+			.enumerate()
+			.filter(|&(i, &b)| b == b'\n' && i + 1 < buffer.len())
+			.map(|(i, _)| i + 1)
+	);
+	starts
+}
+
+pub fn line_range(buffer: &[u8], start: usize, end: usize) -> (usize, usize) {
+	/* the bytes of the whole lines a match from start to end touches, less
+	 * the last line's terminator. a match that ends by taking a '\n' ends
+	 * on the line that '\n' belongs to, not on the next one.
+	 */
+	let last = if end > start { end - 1 } else { start };
+	let first = buffer[..start].iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+	let stop = buffer[last.min(buffer.len())..]
+		.iter()
+		.position(|&b| b == b'\n')
+		.map_or(buffer.len(), |i| last + i + 1); // taking the '\n' along
+	
+	(first, first + trim_terminator(&buffer[first..stop]).len())
+}
+
+pub fn cover(buffer: &[u8], matches: &[(usize, usize)]) -> Covered {
+	let starts = line_starts(buffer);
+	let mut covered = Covered {
+		lines: vec![false; starts.len()],
+		starts: vec![0; starts.len()],
+		spans: vec![Vec::new(); starts.len()],
+	};
+	// the line holding byte i
+	let line_of = |i: usize| starts.partition_point(|&start| start <= i) - 1;
+	
+	for &(s, e) in matches {
+		let first = line_of(s);
+		let last = line_of(if e > s { e - 1 } else { s });
+		covered.starts[first] += 1;
+		
+		for line in first..=last {
+			covered.lines[line] = true;
+			
+			// the part of the match on this line, as offsets into the line
+			let from = starts[line];
+			let text = &buffer[from..starts.get(line + 1).copied().unwrap_or(buffer.len())];
+			let to = from + trim_terminator(text).len();
+			let (s, e) = (s.clamp(from, to), e.clamp(from, to));
+			if e > s {
+				covered.spans[line].push((s - from, e - from));
+			}
+		}
+	}
+	
+	covered
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const CONTENTS: &[u8] = b"Rust:\r\nsafe, fast, productive.\nPick three.\nTrust me.\n";
+	
+	#[test]
+	fn ranges_are_whole_lines() {
+		assert_eq!(vec![0, 7, 31, 43], line_starts(CONTENTS));
+		assert_eq!(vec![0, 2], line_starts(b"a\nb"));
+		
+		// "fast, productive.\nPick" runs from the second line into the third
+		assert_eq!((7, 42), line_range(CONTENTS, 13, 35));
+		// taking the '\n' at the end of a line doesn't take the next one
+		assert_eq!((7, 30), line_range(CONTENTS, 26, 31));
+		assert_eq!((0, 5), line_range(CONTENTS, 0, 0));
+	}
+	
+	#[test]
+	fn covering_lines() {
+		let covered = cover(CONTENTS, &[(3, 9), (13, 17), (20, 30), (53, 53)]);
+		
+		assert_eq!(vec![true, true, false, true], covered.lines);
+		assert_eq!(vec![1, 2, 0, 1], covered.starts);
+		assert_eq!(vec![(3, 5)], covered.spans[0]);
+		// the "\r\n" isn't anything to highlight, the "sa" after it is
+		assert_eq!(vec![(0, 2), (6, 10), (13, 23)], covered.spans[1]);
+		// an empty match still counts, it just has nothing to show
+		assert!(covered.spans[3].is_empty());
+		
+		// up to the end of the last line, and not a byte further
+		assert_eq!(vec![(6, 9)], cover(CONTENTS, &[(49, 53)]).spans[3]);
+	}
+}
@@ -0,0 +1,82 @@
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use crate::{search_input, Config, Input, Printer, SearchError, Searcher};
+
+// everything a worker found out about one input, tagged with its position
+struct Done {
+	index: usize,
+	output: Vec<u8>,
+	grouped: bool, // whether it printed any context groups
+	result: Result<usize, SearchError>,
+}
+
+pub(crate) fn search_in_parallel(
+	config: &Config,
+	searcher: &Searcher,
+	inputs: &[Input],
+	with_names: bool,
+	threads: usize,
+	mut finished: impl FnMut(&Input, Vec<u8>, bool, Result<usize, SearchError>) -> bool,
+) {
+	/* a pool of workers takes input indexes off one shared channel and
+	 * searches into a buffer of their own, so lines from different files can
+	 * never interleave. the buffers come back over a second channel and are
+	 * handed to `finished` in the original order, exactly as a single thread
+	 * would have produced them. `finished` returns false to stop early.
+	 */
+	let (job_tx, job_rx) = mpsc::channel::<usize>();
+	// Mutex so that only one worker at a time can pull the next job
+	let job_rx = Mutex::new(job_rx);
+	let (done_tx, done_rx) = mpsc::channel::<Done>();
+	
+	thread::scope(|scope| {
+		for _ in 0..threads {
+			let job_rx = &job_rx;
+			let done_tx = done_tx.clone();
+			
+			scope.spawn(move || loop {
+				// the lock guard is dropped at the end of this statement
+				let job = job_rx.lock().unwrap().recv();
+				let Ok(index) = job else {
+					break; // the job channel was closed, nothing left to do
+				};
+				
+				let input = &inputs[index];
+				let mut printer = Printer::new(config, searcher.matcher(), Vec::new(), with_names);
+				let result = search_input(config, searcher, input, &mut printer);
+				
+				let done = Done {
+					index, output: printer.out, grouped: printer.printed_group, result
+				};
+				if done_tx.send(done).is_err() {
+					break; // the main thread stopped listening
+				}
+			});
+		}
+		// only the workers' clones should keep the results channel open
+		drop(done_tx);
+		
+		for index in 0..inputs.len() {
+			job_tx.send(index).unwrap();
+		}
+		drop(job_tx);
+		
+		// results arrive as workers finish them, put them back in line
+		let mut pending = BTreeMap::new();
+		let mut next = 0;
+		
+		for done in done_rx {
+			pending.insert(done.index, done);
+			
+			while let Some(done) = pending.remove(&next) {
+				next += 1;
+				if !finished(&inputs[done.index], done.output, done.grouped, done.result) {
+					// dropping the receiver makes the workers give up too
+					return;
+				}
+			}
+		}
+	});
+}
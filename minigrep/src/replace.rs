@@ -0,0 +1,231 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::binary::{self, BinaryFiles};
+use crate::Matcher;
+
+pub(crate) fn edit_in_place(
+	path: &Path,
+	matcher: &dyn Matcher,
+	replacement: &[u8],
+	expand: bool,
+	backup_suffix: Option<&str>,
+	binary_files: BinaryFiles,
+) -> io::Result<Option<usize>> {
+	/* the edited text goes to a temporary file next to the original, and only
+	 * once all of it is safely written does a rename swap it into place.
+	 * rename is atomic, so anyone reading the file sees either the old text
+	 * or the new text, and if anything fails the original is left alone.
+	 * returns how many replacements were made, or None for a binary file
+	 * that was left alone because -a wasn't given.
+	 */
+	// a symlink's target is what gets edited, not replaced by a plain file
+	let path = &fs::canonicalize(path)?;
+	let mut original = BufReader::new(File::open(path)?);
+	if binary_files != BinaryFiles::Text && binary::sniff(&mut original)? {
+		return Ok(None);
+	}
+	
+	let permissions = original.get_ref().metadata()?.permissions();
+	let temp_path = temp_path_for(path)?;
+	// create_new so we never clobber, or later clean up, something already there
+	let temp = OpenOptions::new().write(true).create_new(true).open(&temp_path)?;
+	
+	let edited = write_edited(original, temp, matcher, replacement, expand);
+	let replaced = match edited {
+		Ok(0) | Err(_) => {
+			// nothing changed or something broke, either way the temp goes
+			let _ = fs::remove_file(&temp_path);
+			return edited.map(Some);
+		}
+		Ok(replaced) => replaced,
+	};
+	
+	let swapped = fs::set_permissions(&temp_path, permissions)
+		.and_then(|()| match backup_suffix {
+			Some(suffix) => fs::copy(path, backup_path_for(path, suffix)).map(|_| ()),
+			None => Ok(()),
+		})
+		.and_then(|()| fs::rename(&temp_path, path));
+	
+	if let Err(e) = swapped {
+		let _ = fs::remove_file(&temp_path);
+		return Err(e);
+	}
+	
+	Ok(Some(replaced))
+}
+
+fn write_edited(
+	mut reader: impl BufRead,
+	temp: File,
+	matcher: &dyn Matcher,
+	replacement: &[u8],
+	expand: bool,
+) -> io::Result<usize> {
+	let mut out = BufWriter::new(temp);
+	let mut buf = Vec::new();
+	let mut replaced = 0;
+	
+	loop {
+		buf.clear();
+		if reader.read_until(b'\n', &mut buf)? == 0 {
+			break;
+		}
+		
+		// edit the line itself and put its own terminator back afterwards
+		let body_len = buf.len() - terminator_len(&buf);
+		let (body, terminator) = buf.split_at(body_len);
+		
+		if matcher.is_match(body) {
+			let (edited, count) = matcher.replace_all(body, replacement, expand);
+			out.write_all(&edited)?;
+			replaced += count;
+		} else {
+			out.write_all(body)?;
+		}
+		out.write_all(terminator)?;
+	}
+	
+	// make sure it's all really on disk before it replaces the original
+	out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+	Ok(replaced)
+}
+
+fn terminator_len(line: &[u8]) -> usize {
+	if line.ends_with(b"\r\n") {
+		2
+	} else if line.ends_with(b"\n") {
+		1
+	} else {
+		0
+	}
+}
+
+fn temp_path_for(path: &Path) -> io::Result<PathBuf> {
+	// the same directory keeps the rename on one filesystem
+	let name = path.file_name().ok_or_else(|| io::Error::other("not a file name"))?;
+	let temp_name = format!(".{}.minigrep-{}", name.to_string_lossy(), process::id());
+	Ok(path.with_file_name(temp_name))
+}
+
+fn backup_path_for(path: &Path, suffix: &str) -> PathBuf {
+	let mut backup = path.as_os_str().to_owned();
+	backup.push(suffix);
+	PathBuf::from(backup)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::env;
+	use crate::{Config, PatternMatcher};
+	
+	fn matcher(flags: &[&str]) -> PatternMatcher {
+		let mut args = vec!["minigrep"];
+		args.extend(flags);
+		PatternMatcher::new(&Config::from_args(args.into_iter().map(String::from)).unwrap()).unwrap()
+	}
+	
+	fn scratch(name: &str, contents: &str) -> PathBuf {
+		// the process id keeps two test runs out of each other's way
+		let dir = env::temp_dir().join(format!("{name}_{}", process::id()));
+		let _ = fs::remove_dir_all(&dir);
+		fs::create_dir_all(&dir).unwrap();
+		let path = dir.join("poem.txt");
+		fs::write(&path, contents).unwrap();
+		path
+	}
+	
+	#[test]
+	fn rewrites_and_keeps_backup() {
+		let path = scratch("minigrep_rewrites_and_keeps_backup", "a frog\r\nno match\nfrog frog");
+		let replaced = edit_in_place(
+			&path, &matcher(&["frog"]), b"toad", false, Some(".bak"), BinaryFiles::Binary
+		).unwrap();
+		
+		assert_eq!(Some(3), replaced);
+		// line endings, even a missing last one, come through unchanged
+		assert_eq!("a toad\r\nno match\ntoad toad", fs::read_to_string(&path).unwrap());
+		assert_eq!("a frog\r\nno match\nfrog frog",
+			fs::read_to_string(path.with_file_name("poem.txt.bak")).unwrap());
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+	
+	#[test]
+	fn capture_references() {
+		let path = scratch("minigrep_capture_references", "How public, like a frog\n");
+		let replaced = edit_in_place(
+			&path, &matcher(&["-E", r"like a (?<animal>\w+)"]), b"a ${animal}-like", true, None,
+			BinaryFiles::Binary
+		).unwrap();
+		
+		assert_eq!(Some(1), replaced);
+		assert_eq!("How public, a frog-like\n", fs::read_to_string(&path).unwrap());
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+	
+	#[test]
+	fn untouched_without_matches() {
+		let path = scratch("minigrep_untouched_without_matches", "nothing here\n");
+		let replaced = edit_in_place(
+			&path, &matcher(&["frog"]), b"toad", false, Some(".bak"), BinaryFiles::Binary
+		).unwrap();
+		
+		assert_eq!(Some(0), replaced);
+		assert!(!path.with_file_name("poem.txt.bak").exists());
+		assert_eq!(1, fs::read_dir(path.parent().unwrap()).unwrap().count());
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+	
+	#[test]
+	fn untouched_on_error() {
+		let path = scratch("minigrep_untouched_on_error", "a frog\n");
+		// something already sitting where the temp file should go
+		let blocker = temp_path_for(&path).unwrap();
+		fs::write(&blocker, "in the way").unwrap();
+		
+		let frog = matcher(&["frog"]);
+		assert!(edit_in_place(&path, &frog, b"toad", false, None, BinaryFiles::Binary).is_err());
+		assert_eq!("a frog\n", fs::read_to_string(&path).unwrap());
+		assert_eq!("in the way", fs::read_to_string(&blocker).unwrap());
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+	
+	#[test]
+	fn binary_left_alone_without_text() {
+		let path = scratch("minigrep_binary_left_alone_without_text", "");
+		fs::write(&path, b"frog\0bin\n").unwrap();
+		let frog = matcher(&["frog"]);
+		
+		assert_eq!(None, edit_in_place(&path, &frog, b"toad", false, None, BinaryFiles::Binary).unwrap());
+		assert_eq!(b"frog\0bin\n", &fs::read(&path).unwrap()[..]);
+		
+		// -a: it's text after all
+		assert_eq!(Some(1), edit_in_place(&path, &frog, b"toad", false, None, BinaryFiles::Text).unwrap());
+		assert_eq!(b"toad\0bin\n", &fs::read(&path).unwrap()[..]);
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+	
+	#[cfg(unix)]
+	#[test]
+	fn symlinks_edit_their_target() {
+		let path = scratch("minigrep_symlinks_edit_their_target", "a frog\n");
+		let link = path.with_file_name("link.txt");
+		std::os::unix::fs::symlink("poem.txt", &link).unwrap();
+		
+		let frog = matcher(&["frog"]);
+		assert_eq!(Some(1), edit_in_place(&link, &frog, b"toad", false, None, BinaryFiles::Binary).unwrap());
+		assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+		assert_eq!("a toad\n", fs::read_to_string(&path).unwrap());
+		
+		fs::remove_dir_all(path.parent().unwrap()).unwrap();
+	}
+}
@@ -0,0 +1,394 @@
+/*
+ * The search itself, for anyone who wants the results rather than minigrep's
+ * output. A Searcher runs a Matcher over an input and tells a Sink about
+ * everything it finds on the way: where the input begins, each selected line
+ * and the context around it, and what it all added up to at the end. What
+ * the Sink does with that is up to it, printing like the command line does
+ * is just one choice.
+ */
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use encoding_rs::Encoding;
+
+use crate::binary::{self, BinaryFiles};
+use crate::decompress;
+use crate::encoding;
+use crate::matcher::Matcher;
+use crate::multiline;
+use crate::stream::{search_reader, ByteLine, Event};
+
+// what went wrong while searching a single input
+#[derive(Debug)]
+pub enum SearchError {
+	Input(io::Error),  // couldn't read it, report that and move on
+	Output(io::Error), // the Sink failed, time to stop
+}
+
+impl fmt::Display for SearchError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SearchError::Input(e) => write!(f, "{e}"),
+			SearchError::Output(e) => write!(f, "output failed: {e}"),
+		}
+	}
+}
+
+impl Error for SearchError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			SearchError::Input(e) | SearchError::Output(e) => Some(e),
+		}
+	}
+}
+
+// a selected line, and the way to what in it matched
+pub struct SinkMatch<'a> {
+	pub line: ByteLine<'a>,
+	spans: Spans<'a>,
+}
+
+enum Spans<'a> {
+	Known(&'a [(usize, usize)]), // multiline searching found them already
+	Find(&'a dyn Matcher),       // only looked for if someone asks
+	Nothing,                     // selected by -v, so nothing in it matched
+}
+
+impl SinkMatch<'_> {
+	pub fn spans(&self) -> Vec<(usize, usize)> {
+		// the matched parts of the line as byte offsets, empty ones left out
+		match self.spans {
+			Spans::Known(spans) => spans.to_vec(),
+			Spans::Find(matcher) => matcher.find_all(self.line.bytes),
+			Spans::Nothing => Vec::new(),
+		}
+	}
+}
+
+// how a search of one input turned out
+pub struct Finish<'a> {
+	pub path: &'a str,
+	pub matched_lines: usize,
+	pub matches: usize, // the same as matched_lines, but for multiline matches
+	pub binary: bool,   // it looked binary, so binary_files had its say
+}
+
+/* everything but on_match has nothing to do by default, so a Sink that only
+ * wants the matching lines is a single method. an error from any of them
+ * stops the search and comes back as SearchError::Output.
+ */
+pub trait Sink {
+	fn on_begin_file(&mut self, _path: &str) -> io::Result<()> {
+		Ok(())
+	}
+	
+	fn on_match(&mut self, found: &SinkMatch) -> io::Result<()>;
+	
+	fn on_context(&mut self, _line: &ByteLine) -> io::Result<()> {
+		Ok(())
+	}
+	
+	// context lines come in groups, this is called before each one
+	fn on_group_start(&mut self) -> io::Result<()> {
+		Ok(())
+	}
+	
+	fn on_finish(&mut self, _finish: &Finish) -> io::Result<()> {
+		Ok(())
+	}
+}
+
+pub struct Searcher<'m> {
+	matcher: &'m dyn Matcher,
+	pub before_context: usize,
+	pub after_context: usize,
+	pub max_count: Option<usize>, // stop after this many selected lines
+	pub invert_match: bool,       // select the lines that don't match
+	pub multiline: bool,          // match against the whole input at once
+	pub binary_files: BinaryFiles,
+	pub search_zip: bool,         // decompress gzip and the like first
+	pub encoding: Option<&'static Encoding>, // what the text is in, if there's no BOM
+}
+
+impl<'m> Searcher<'m> {
+	pub fn new(matcher: &'m dyn Matcher) -> Searcher<'m> {
+		// the same as minigrep with no options, set the fields to change that
+		Searcher {
+			matcher,
+			before_context: 0,
+			after_context: 0,
+			max_count: None,
+			invert_match: false,
+			multiline: false,
+			binary_files: BinaryFiles::Binary,
+			search_zip: false,
+			encoding: None,
+		}
+	}
+	
+	pub fn matcher(&self) -> &'m dyn Matcher {
+		self.matcher
+	}
+	
+	pub fn search_path<S: Sink + ?Sized>(
+		&self, path: &Path, sink: &mut S
+	) -> Result<usize, SearchError> {
+		let file = File::open(path).map_err(SearchError::Input)?;
+		self.search_reader(&path.display().to_string(), BufReader::new(file), sink)
+	}
+	
+	pub fn search_reader<S: Sink + ?Sized>(
+		&self, path: &str, reader: impl BufRead, sink: &mut S
+	) -> Result<usize, SearchError> {
+		/* searches reader to its end, or to max_count, telling sink about it
+		 * as it goes. path is only passed on, it's whatever the sink should
+		 * call this input. returns how many lines were selected.
+		 */
+		// which reader we end up with is only known once it's been peeked at
+		let reader: Box<dyn BufRead + '_> = if self.search_zip {
+			decompress::decompress(Box::new(reader)).map_err(SearchError::Input)?
+		} else {
+			Box::new(reader)
+		};
+		// UTF-16 and the like are searched, and reported, as UTF-8
+		let mut reader = encoding::transcode(reader, self.encoding).map_err(SearchError::Input)?;
+		
+		let binary = self.binary_files != BinaryFiles::Text
+			&& binary::sniff(&mut reader).map_err(SearchError::Input)?;
+		// searching nothing at all still ends with a count of 0
+		let skipped = binary && self.binary_files == BinaryFiles::WithoutMatch;
+		// the only line of a binary file that matters is the first match
+		let withheld = binary && self.binary_files == BinaryFiles::Binary;
+		
+		/* multiline searching takes all of the input in one go, then says line
+		 * by line what it found, so the lines can still be streamed as usual.
+		 */
+		let mut buffer = Vec::new();
+		let covered = if self.multiline && !skipped {
+			reader.read_to_end(&mut buffer).map_err(SearchError::Input)?;
+			Some(multiline::cover(&buffer, &self.matcher.find_every(&buffer)))
+		} else {
+			None
+		};
+		
+		let mut nothing = io::empty();
+		let mut whole = &buffer[..];
+		let lines: &mut dyn BufRead = if skipped {
+			&mut nothing
+		} else if covered.is_some() {
+			&mut whole
+		} else {
+			&mut reader
+		};
+		
+		sink.on_begin_file(path).map_err(SearchError::Output)?;
+		
+		let (max, before, after) = if withheld {
+			(Some(1), 0, 0)
+		} else {
+			(self.max_count, self.before_context, self.after_context)
+		};
+		let mut next_line = 0;
+		let mut matches = 0; // for multiline, matches rather than lines
+		// a failed Sink is the caller's problem, not the input's, so keep it aside
+		let mut sink_error = None;
+		
+		let searched = search_reader(
+			lines,
+			|line| match &covered {
+				// asked once per line, in order, for as long as it matters
+				Some(covered) => {
+					next_line += 1;
+					if covered.lines[next_line - 1] && !self.invert_match {
+						matches += covered.starts[next_line - 1];
+					}
+					covered.lines[next_line - 1] != self.invert_match
+				}
+				None => self.matcher.is_match(line) != self.invert_match,
+			},
+			before,
+			after,
+			max,
+			|event| {
+				if withheld {
+					return Ok(());
+				}
+				
+				let told = match event {
+					Event::GroupStart => sink.on_group_start(),
+					Event::Match(line) => {
+						let spans = match &covered {
+							_ if self.invert_match => Spans::Nothing,
+							Some(covered) => Spans::Known(&covered.spans[line.line_number - 1]),
+							None => Spans::Find(self.matcher),
+						};
+						sink.on_match(&SinkMatch { line, spans })
+					}
+					Event::Context(line) => sink.on_context(&line),
+				};
+				
+				told.map_err(|e| {
+					sink_error = Some(e);
+					io::Error::other("output failed")
+				})
+			},
+		);
+		
+		if let Some(e) = sink_error {
+			return Err(SearchError::Output(e));
+		}
+		let matched_lines = searched.map_err(SearchError::Input)?;
+		
+		// with -v it's still lines being picked, however they matched
+		let finish = Finish {
+			path,
+			matched_lines,
+			matches: if covered.is_some() && !self.invert_match { matches } else { matched_lines },
+			binary,
+		};
+		sink.on_finish(&finish).map_err(SearchError::Output)?;
+		
+		Ok(matched_lines)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	
+	const CONTENTS: &[u8] = b"\
+Rust:
+safe, fast, productive.
+Pick three.
+Trust me.
+";
+	
+	// matches any 's', the smallest Matcher there is
+	struct Letter(u8);
+	
+	impl Matcher for Letter {
+		fn find_at(&self, line: &[u8], start: usize) -> Option<(usize, usize)> {
+			let found = line.get(start..)?.iter().position(|&b| b == self.0)?;
+			Some((start + found, start + found + 1))
+		}
+	}
+	
+	// everything a search said, written down as text
+	#[derive(Default)]
+	struct Notes(Vec<String>);
+	
+	impl Sink for Notes {
+		fn on_begin_file(&mut self, path: &str) -> io::Result<()> {
+			self.0.push(format!("begin {path}"));
+			Ok(())
+		}
+		
+		fn on_match(&mut self, found: &SinkMatch) -> io::Result<()> {
+			self.0.push(format!("{}:{:?}", found.line.line_number, found.spans()));
+			Ok(())
+		}
+		
+		fn on_context(&mut self, line: &ByteLine) -> io::Result<()> {
+			self.0.push(format!("{}-", line.line_number));
+			Ok(())
+		}
+		
+		fn on_group_start(&mut self) -> io::Result<()> {
+			self.0.push(String::from("--"));
+			Ok(())
+		}
+		
+		fn on_finish(&mut self, finish: &Finish) -> io::Result<()> {
+			self.0.push(format!(
+				"end {} {} {} {}", finish.path, finish.matched_lines, finish.matches, finish.binary
+			));
+			Ok(())
+		}
+	}
+	
+	fn notes(searcher: &Searcher, contents: &[u8]) -> Vec<String> {
+		let mut notes = Notes::default();
+		searcher.search_reader("poem", contents, &mut notes).unwrap();
+		notes.0
+	}
+	
+	#[test]
+	fn reports_every_step() {
+		let matcher = Letter(b'f');
+		let mut searcher = Searcher::new(&matcher);
+		assert_eq!(vec!["begin poem", "2:[(2, 3), (6, 7)]", "end poem 1 1 false"],
+			notes(&searcher, CONTENTS));
+		
+		searcher.before_context = 1;
+		assert_eq!(
+			vec!["begin poem", "--", "1-", "2:[(2, 3), (6, 7)]", "end poem 1 1 false"],
+			notes(&searcher, CONTENTS)
+		);
+	}
+	
+	#[test]
+	fn inverted_lines_have_no_spans() {
+		let matcher = Letter(b'k');
+		let mut searcher = Searcher::new(&matcher);
+		searcher.invert_match = true;
+		assert_eq!(
+			vec!["begin poem", "1:[]", "2:[]", "4:[]", "end poem 3 3 false"],
+			notes(&searcher, CONTENTS)
+		);
+		
+		searcher.max_count = Some(1);
+		assert_eq!(vec!["begin poem", "1:[]", "end poem 1 1 false"], notes(&searcher, CONTENTS));
+	}
+	
+	#[test]
+	fn multiline_counts_matches() {
+		let matcher = Letter(b'\n');
+		let mut searcher = Searcher::new(&matcher);
+		searcher.multiline = true;
+		// a '\n' is never part of a line, only of a match, so nothing to show
+		assert_eq!(
+			vec!["begin poem", "1:[]", "2:[]", "end poem 2 2 false"],
+			notes(&searcher, b"one\ntwo\n")
+		);
+		
+		let matcher = Letter(b's');
+		let searcher = Searcher { multiline: true, ..Searcher::new(&matcher) };
+		assert_eq!("end poem 3 4 false", notes(&searcher, CONTENTS)[4]);
+	}
+	
+	#[test]
+	fn binary_lines_are_held_back() {
+		let matcher = Letter(b's');
+		let mut searcher = Searcher::new(&matcher);
+		let binary = b"s\x00\nsss\n";
+		assert_eq!(vec!["begin poem", "end poem 1 1 true"], notes(&searcher, binary));
+		
+		searcher.binary_files = BinaryFiles::WithoutMatch;
+		assert_eq!(vec!["begin poem", "end poem 0 0 true"], notes(&searcher, binary));
+		
+		searcher.binary_files = BinaryFiles::Text;
+		assert_eq!(4, notes(&searcher, binary).len());
+	}
+	
+	#[test]
+	fn sink_errors_stop_the_search() {
+		struct Broken;
+		impl Sink for Broken {
+			fn on_match(&mut self, _: &SinkMatch) -> io::Result<()> {
+				Err(io::Error::from(io::ErrorKind::BrokenPipe))
+			}
+		}
+		
+		let matcher = Letter(b's');
+		let searcher = Searcher::new(&matcher);
+		let result = searcher.search_reader("poem", CONTENTS, &mut Broken);
+		assert!(matches!(result, Err(SearchError::Output(e)) if e.kind() == io::ErrorKind::BrokenPipe));
+		
+		let result = searcher.search_path(Path::new("/no/such/poem"), &mut Broken);
+		assert!(matches!(result, Err(SearchError::Input(_))));
+	}
+}
@@ -0,0 +1,230 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ByteLine<'a> {
+	pub line_number: usize, // counting from 1, the way editors do
+	pub byte_offset: usize, // where the line starts within the input
+	pub bytes: &'a [u8],    // without its line terminator
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Event<'a> {
+	GroupStart, // only sent when context lines were asked for
+	Match(ByteLine<'a>),
+	Context(ByteLine<'a>),
+}
+
+pub fn search_reader<R: BufRead>(
+	mut reader: R,
+	mut is_match: impl FnMut(&[u8]) -> bool,
+	before: usize,
+	after: usize,
+	max: Option<usize>,
+	mut emit: impl FnMut(Event) -> io::Result<()>,
+) -> io::Result<usize> {
+	/* one line is read at a time into a buffer we keep reusing, and the
+	 * only other thing we hold on to is the last `before` lines, so memory
+	 * use doesn't grow with the size of the input. lines are plain bytes,
+	 * so invalid UTF-8 is fine. after `max` matches only the trailing
+	 * context is still read, then the rest of the input is left alone.
+	 * returns how many lines matched.
+	 */
+	let with_context = before > 0 || after > 0;
+	let mut buf = Vec::new();
+	let mut line_number = 0;
+	let mut byte_offset = 0;
+	let mut count = 0;
+	
+	// skipped lines that may still turn out to be leading context
+	let mut history: VecDeque<(usize, usize, Vec<u8>)> = VecDeque::with_capacity(before);
+	let mut after_left = 0; // trailing context lines still owed
+	let mut last_shown: Option<usize> = None;
+	
+	loop {
+		let done = max.is_some_and(|max| count >= max);
+		if done && after_left == 0 {
+			break;
+		}
+		
+		buf.clear();
+		let read = reader.read_until(b'\n', &mut buf)?;
+		if read == 0 {
+			break; // end of input
+		}
+		
+		line_number += 1;
+		let line = ByteLine { line_number, byte_offset, bytes: trim_terminator(&buf) };
+		byte_offset += read;
+		
+		if !done && is_match(line.bytes) {
+			count += 1;
+			
+			if with_context {
+				// a gap since the last shown line means a new group
+				let first = history.front().map_or(line_number, |(n, _, _)| *n);
+				if last_shown.is_none_or(|last| first > last + 1) {
+					emit(Event::GroupStart)?;
+				}
+				
+				for (line_number, byte_offset, bytes) in history.drain(..) {
+					emit(Event::Context(ByteLine { line_number, byte_offset, bytes: &bytes }))?;
+				}
+			}
+			
+			emit(Event::Match(line))?;
+			last_shown = Some(line_number);
+			after_left = after;
+		} else if after_left > 0 {
+			emit(Event::Context(line))?;
+			last_shown = Some(line_number);
+			after_left -= 1;
+		} else if before > 0 {
+			if history.len() == before {
+				history.pop_front();
+			}
+			history.push_back((line_number, line.byte_offset, line.bytes.to_vec()));
+		}
+	}
+	
+	Ok(count)
+}
+
+pub fn trim_terminator(line: &[u8]) -> &[u8] {
+	// strip the terminator the same way str::lines does, "\r\n" included
+	match line.strip_suffix(b"\n") {
+		Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+		None => line,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::tests::{context_groups, search_positions};
+	
+	// flatten events into (line number, is a match) with 0 for a group start
+	fn events(input: &[u8], query: &[u8], before: usize, after: usize) -> Vec<(usize, bool)> {
+		events_up_to(input, query, before, after, None)
+	}
+	
+	fn events_up_to(
+		input: &[u8], query: &[u8], before: usize, after: usize, max: Option<usize>
+	) -> Vec<(usize, bool)> {
+		let mut seen = Vec::new();
+		search_reader(
+			input,
+			|line| line.windows(query.len()).any(|w| w == query),
+			before,
+			after,
+			max,
+			|event| {
+				seen.push(match event {
+					Event::GroupStart => (0, false),
+					Event::Match(line) => (line.line_number, true),
+					Event::Context(line) => (line.line_number, false),
+				});
+				Ok(())
+			},
+		).unwrap();
+		seen
+	}
+	
+	#[test]
+	fn reader_agrees_with_search_positions() {
+		let contents = "\
+Rust:
+safe, fast, productive.\r
+Pick three.
+Duct tape.";
+		let mut streamed = Vec::new();
+		
+		let count = search_reader(
+			contents.as_bytes(),
+			|line| line.windows(2).any(|w| w == b"ct"),
+			0,
+			0,
+			None,
+			|event| {
+				if let Event::Match(line) = event {
+					streamed.push((line.line_number, line.byte_offset, line.bytes.to_vec()));
+				}
+				Ok(())
+			},
+		).unwrap();
+		
+		let expected: Vec<_> = search_positions(contents, |line| line.contains("ct"))
+			.into_iter()
+			.map(|found| (found.line_number, found.byte_offset, found.line.as_bytes().to_vec()))
+			.collect();
+		
+		assert_eq!(2, count);
+		assert_eq!(expected, streamed);
+	}
+	
+	#[test]
+	fn reader_tolerates_invalid_utf8() {
+		let input = b"caf\xe9 latin-1\nplain\n\xff\xfe rust \xff\n";
+		
+		assert_eq!(vec![(3, true)], events(input, b"rust", 0, 0));
+		assert_eq!(vec![(1, true)], events(input, b"caf\xe9", 0, 0));
+	}
+	
+	#[test]
+	fn reader_context_agrees_with_context_groups() {
+		let contents = "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\n";
+		
+		for (before, after) in [(1, 1), (2, 0), (0, 3), (3, 3)] {
+			let results = search_positions(contents, |line| line.contains("e"));
+			let mut expected = Vec::new();
+			for group in context_groups(contents, &results, before, after) {
+				expected.push((0, false));
+				expected.extend(group.iter().map(|(l, m)| (l.line_number, *m)));
+			}
+			
+			assert_eq!(expected, events(contents.as_bytes(), b"e", before, after));
+		}
+	}
+	
+	#[test]
+	fn reader_handles_long_inputs() {
+		// a million lines from a repeating reader, never in memory all at once
+		let input = io::Read::chain(io::Read::take(io::repeat(b'\n'), 1 << 20), &b"needle\n"[..]);
+		let input = io::BufReader::new(input);
+		let count = search_reader(input, |line| line == b"needle", 0, 0, None, |_| Ok(())).unwrap();
+		
+		assert_eq!(1, count);
+		
+		// a single line a megabyte long does have to be held whole, but it's no trouble either
+		let input = io::Read::chain(io::Read::take(io::repeat(b'x'), 1 << 20), &b"\nneedle\n"[..]);
+		let input = io::BufReader::new(input);
+		let count = search_reader(input, |line| line == b"needle", 0, 0, None, |_| Ok(())).unwrap();
+		
+		assert_eq!(1, count);
+	}
+	
+	#[test]
+	fn reader_stops_at_max() {
+		let contents = b"one\ntwo\nthree\nfour\nfive\nsix\nseven\n";
+		
+		assert_eq!(vec![(1, true), (3, true)], events_up_to(contents, b"e", 0, 0, Some(2)));
+		// the trailing context still comes, even when it would have matched
+		assert_eq!(
+			vec![(0, false), (1, true), (2, false), (3, false)],
+			events_up_to(contents, b"e", 0, 2, Some(1))
+		);
+		assert!(events_up_to(contents, b"e", 1, 1, Some(0)).is_empty());
+		
+		// nothing past the first match is read at all
+		let mut lines = 0;
+		let count = search_reader(
+			io::BufReader::new(io::Read::chain(&b"needle\n"[..], io::Read::take(io::repeat(b'x'), 1 << 20))),
+			|_| {
+				lines += 1;
+				true
+			},
+			0, 0, Some(1), |_| Ok(()),
+		).unwrap();
+		assert_eq!((1, 1), (count, lines));
+	}
+}
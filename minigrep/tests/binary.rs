@@ -0,0 +1,66 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+mod common;
+use common::minigrep;
+
+fn fixture(name: &str) -> PathBuf {
+	/* something shaped like a small executable: a header full of NULs, then
+	 * a string table that happens to contain the words we search for
+	 */
+	let path = env::temp_dir().join(name);
+	let mut bytes = b"\x7fELF\x02\x01\x01\x00\x00\x00\x00\x00\x00\x00\x00\x00".to_vec();
+	bytes.extend(b"\x03\x00\x3e\x00\x01\x00\x00\x00\xff\xfe\n");
+	bytes.extend(b"frog\x00public\x00bog\n");
+	bytes.extend(b"\x90\x90\xc3 frog again\n");
+	fs::write(&path, bytes).unwrap();
+	path
+}
+
+#[test]
+fn binary_is_the_default() {
+	let path = fixture("minigrep_binary_is_the_default.bin");
+	let name = path.to_str().unwrap();
+	
+	let output = minigrep(&["frog", name]);
+	assert!(output.status.success());
+	assert_eq!(format!("Binary file {name} matches\n").as_bytes(), &output.stdout[..]);
+	
+	// nothing matched, so nothing to say
+	let output = minigrep(&["toad", name]);
+	assert!(output.stdout.is_empty());
+	
+	// counting still reads the whole thing
+	let output = minigrep(&["-c", "frog", name]);
+	assert_eq!(b"2\n", &output.stdout[..]);
+	
+	fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn text_prints_the_raw_lines() {
+	let path = fixture("minigrep_text_prints_the_raw_lines.bin");
+	let name = path.to_str().unwrap();
+	
+	let output = minigrep(&["--binary-files=text", "-n", "frog", name]);
+	assert_eq!(&b"2:frog\x00public\x00bog\n3:\x90\x90\xc3 frog again\n"[..], &output.stdout[..]);
+	assert_eq!(output.stdout, minigrep(&["-an", "frog", name]).stdout);
+	
+	fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn without_match_skips_binary_files() {
+	let path = fixture("minigrep_without_match_skips_binary_files.bin");
+	let name = path.to_str().unwrap();
+	
+	let output = minigrep(&["--binary-files=without-match", "frog", name]);
+	assert!(output.stdout.is_empty());
+	
+	let poem = concat!(env!("CARGO_MANIFEST_DIR"), "/poem.txt");
+	let output = minigrep(&["--binary-files=without-match", "-c", "frog", name, poem]);
+	assert_eq!(format!("{name}:0\n{poem}:1\n"), String::from_utf8(output.stdout).unwrap());
+	
+	fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,47 @@
+/*
+ * Running the real minigrep binary, shared by the integration tests with
+ * `mod common;`. cargo tells us where it was built through the
+ * CARGO_BIN_EXE_<name> environment variable. whatever the person running
+ * the tests has set for themselves is scrubbed here, and only here, so a
+ * new setting Config reads only needs adding to command().
+ */
+
+// each test file uses only some of these
+#![allow(dead_code)]
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+pub fn command() -> Command {
+	// the binary with none of the user's settings: no environment, no MINIGREP_OPTS, no config file
+	let mut command = Command::new(env!("CARGO_BIN_EXE_minigrep"));
+	command
+		.env_remove("IGNORE_CASE")
+		.env_remove("VERBOSE")
+		.env_remove("COUNT")
+		.env_remove("NO_COLOR")
+		.env_remove("MINIGREP_OPTS")
+		.env("MINIGREP_CONFIG", "");
+	command
+}
+
+pub fn minigrep(args: &[&str]) -> Output {
+	command().args(args).output().unwrap()
+}
+
+pub fn minigrep_in_crate(args: &[&str]) -> Output {
+	// run from the crate root so "poem.txt" resolves to the fixture
+	command().current_dir(env!("CARGO_MANIFEST_DIR")).args(args).output().unwrap()
+}
+
+pub fn minigrep_with_stdin(args: &[&str], stdin: &str) -> Output {
+	let mut child = command()
+		.args(args)
+		.stdin(Stdio::piped())
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+	
+	child.wait_with_output().unwrap()
+}
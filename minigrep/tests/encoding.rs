@@ -0,0 +1,65 @@
+mod common;
+use common::minigrep;
+
+/*
+ * the same five lines in each encoding, the last one with a letter that
+ * isn't ASCII. the UTF-16LE one has a BOM and Windows line endings, the way
+ * Windows tools tend to write it.
+ */
+const UTF_16LE: &str = "tests/fixtures/poem.utf-16le.txt";
+const UTF_16BE: &str = "tests/fixtures/poem.utf-16be.txt";
+const UTF_16LE_NO_BOM: &str = "tests/fixtures/poem.utf-16le-nobom.txt";
+const LATIN_1: &str = "tests/fixtures/poem.latin1.txt";
+
+const LAST_LINE: &str = "5:Je suis personne ! Qui \u{ea}tes-vous ?\n";
+
+#[test]
+fn byte_order_marks_are_enough() {
+	for fixture in [UTF_16LE, UTF_16BE] {
+		let output = minigrep(&["-n", "\u{ea}tes", fixture]);
+		assert!(output.status.success(), "{fixture}");
+		assert_eq!(LAST_LINE, String::from_utf8(output.stdout).unwrap(), "{fixture}");
+		
+		// case folding works on the text, not on what it was encoded as
+		let output = minigrep(&["-c", "-i", "NOBODY", fixture]);
+		assert_eq!("2\n", String::from_utf8(output.stdout).unwrap(), "{fixture}");
+	}
+}
+
+#[test]
+fn encoding_names_the_rest() {
+	let output = minigrep(&["-n", "--encoding", "latin1", "\u{ea}tes", LATIN_1]);
+	assert_eq!(LAST_LINE, String::from_utf8(output.stdout).unwrap());
+	
+	let output = minigrep(&["-n", "--encoding=utf-16le", "\u{ea}tes", UTF_16LE_NO_BOM]);
+	assert_eq!(LAST_LINE, String::from_utf8(output.stdout).unwrap());
+	
+	// a BOM says more than the command line can
+	let output = minigrep(&["-n", "--encoding=latin1", "\u{ea}tes", UTF_16BE]);
+	assert_eq!(LAST_LINE, String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn without_encoding_bytes_are_bytes() {
+	// the Latin-1 ê is a lone byte, which a UTF-8 ê can't match
+	let output = minigrep(&["\u{ea}tes", LATIN_1]);
+	assert_eq!(Some(1), output.status.code());
+	
+	// and UTF-16 without a BOM is full of NULs, so it looks binary
+	let output = minigrep(&["-a", "-c", "nobody", UTF_16LE_NO_BOM]);
+	assert_eq!(b"0\n", &output.stdout[..]);
+}
+
+#[test]
+fn every_encoding_reads_the_same() {
+	let expected = minigrep(&["-n", "you", "--encoding=latin1", LATIN_1]).stdout;
+	assert_eq!(3, expected.iter().filter(|&&b| b == b'\n').count());
+	
+	// "\r\n" or '\n', one byte per character or two, the lines are the lines
+	for (fixture, encoding) in [
+		(UTF_16LE, "auto"), (UTF_16BE, "auto"), (UTF_16LE_NO_BOM, "utf-16le"),
+	] {
+		let output = minigrep(&["-n", "you", "--encoding", encoding, fixture]);
+		assert_eq!(expected, output.stdout, "{fixture}");
+	}
+}
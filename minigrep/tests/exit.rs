@@ -0,0 +1,60 @@
+use std::process::Output;
+
+mod common;
+use common::minigrep_in_crate as minigrep;
+
+fn stdout(output: &Output) -> &str {
+	std::str::from_utf8(&output.stdout).unwrap()
+}
+
+#[test]
+fn exit_status_follows_grep() {
+	assert_eq!(Some(0), minigrep(&["frog", "poem.txt"]).status.code());
+	assert_eq!(Some(1), minigrep(&["toad", "poem.txt"]).status.code());
+	assert_eq!(Some(2), minigrep(&["frog", "no-such-file.txt"]).status.code());
+	assert_eq!(Some(2), minigrep(&["--bogus", "frog", "poem.txt"]).status.code());
+	assert_eq!(Some(2), minigrep(&[]).status.code());
+}
+
+#[test]
+fn quiet_prints_nothing() {
+	let found = minigrep(&["-q", "frog", "poem.txt"]);
+	assert_eq!(Some(0), found.status.code());
+	assert!(found.stdout.is_empty());
+	
+	// a match settles it, even with an unreadable file later on
+	let found = minigrep(&["--silent", "frog", "poem.txt", "no-such-file.txt"]);
+	assert_eq!(Some(0), found.status.code());
+	
+	let missing = minigrep(&["-q", "toad", "poem.txt"]);
+	assert_eq!(Some(1), missing.status.code());
+	assert!(missing.stdout.is_empty());
+}
+
+#[test]
+fn listing_files() {
+	let with = minigrep(&["-l", "frog", "poem.txt", "Cargo.toml"]);
+	assert_eq!("poem.txt\n", stdout(&with));
+	assert_eq!(Some(0), with.status.code());
+	
+	let without = minigrep(&["-L", "frog", "poem.txt", "Cargo.toml"]);
+	assert_eq!("Cargo.toml\n", stdout(&without));
+	assert_eq!(Some(0), without.status.code());
+	
+	// -L selects files, so listing none of them is a failure
+	let without = minigrep(&["-L", "frog", "poem.txt"]);
+	assert_eq!("", stdout(&without));
+	assert_eq!(Some(1), without.status.code());
+}
+
+#[test]
+fn max_count_limits_each_file() {
+	let output = minigrep(&["-m", "1", "-n", "How", "poem.txt"]);
+	assert_eq!("11:How dreary to be somebody!\n", stdout(&output));
+	
+	let output = minigrep(&["-c", "-m", "2", "o", "poem.txt", "Cargo.toml"]);
+	assert_eq!("poem.txt:2\nCargo.toml:2\n", stdout(&output));
+	
+	// nothing can be selected from no lines at all
+	assert_eq!(Some(1), minigrep(&["-m", "0", "frog", "poem.txt"]).status.code());
+}
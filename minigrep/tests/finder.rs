@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+use minigrep::{search, search_case_insensitive};
+
+/*
+ * search and search_case_insensitive used to be one-liners over
+ * str::contains, with a lowercase copy of every line for the second. they're
+ * kept here as they were so the Finder based versions have something to be
+ * checked and timed against. `cargo test --release --test finder --
+ * --show-output` prints the timings, a debug build mostly measures bounds
+ * checks. they're only printed, never asserted on, as how fast anything
+ * runs depends on whatever else the machine is doing.
+ */
+
+fn old_search<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	contents.lines()
+			.filter(|line| line.contains(query))
+			.collect()
+}
+
+fn old_search_case_insensitive<'a>(query: &str, contents: &'a str) -> Vec<&'a str> {
+	let query: String = query.to_lowercase();
+	contents.lines()
+			.filter(|line| line.to_lowercase().contains(&query))
+			.collect()
+}
+
+fn big_input() -> String {
+	// lots of lines that almost match, so neither side gets off lightly
+	let poem = include_str!("../poem.txt");
+	let mut contents = String::new();
+	for i in 0..4000 {
+		contents.push_str(poem);
+		contents.push_str(&format!("{i}: {}\n", "a long line of filler text with no frogs in it, ".repeat(8)));
+	}
+	contents
+}
+
+fn best_of<'a>(runs: usize, f: impl Fn() -> Vec<&'a str>) -> (Vec<&'a str>, Duration) {
+	// the fastest run is the one least disturbed by whatever else is going on
+	let mut best = Duration::MAX;
+	let mut found = Vec::new();
+	for _ in 0..runs {
+		let start = Instant::now();
+		found = f();
+		best = best.min(start.elapsed());
+	}
+	(found, best)
+}
+
+#[test]
+fn search_matches_old_search() {
+	let contents = big_input();
+	
+	for query in ["frog", "How dreary", "public, like a", "no such phrase"] {
+		let (old, old_time) = best_of(3, || old_search(query, &contents));
+		let (new, new_time) = best_of(3, || search(query, &contents));
+		println!("search {query:?}: contains {old_time:?}, Finder {new_time:?}");
+		
+		assert_eq!(old, new);
+	}
+}
+
+#[test]
+fn case_insensitive_matches_old_search() {
+	let contents = big_input();
+	
+	for query in ["FROG", "how DREARY", "NOBODY! WHO"] {
+		let (old, old_time) = best_of(5, || old_search_case_insensitive(query, &contents));
+		let (new, new_time) = best_of(5, || search_case_insensitive(query, &contents));
+		println!("search_case_insensitive {query:?}: to_lowercase {old_time:?}, Finder {new_time:?}");
+		
+		assert_eq!(old, new);
+	}
+}
+
+#[test]
+fn case_insensitive_unicode_agrees() {
+	// lines the ASCII shortcut can't handle on its own
+	let contents = "caf\u{e9} \u{c9}T\u{c9}\n42 \u{212a}m away\nplain ascii km\n\u{130}stanbul\n";
+	
+	for query in ["\u{e9}t\u{e9}", "km", "i\u{307}stanbul", "ascii"] {
+		assert_eq!(
+			old_search_case_insensitive(query, contents),
+			search_case_insensitive(query, contents),
+			"{query:?}"
+		);
+	}
+}
@@ -0,0 +1,57 @@
+#![cfg(unix)] // Ctrl-C is sent with kill(1)
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+mod common;
+
+#[test]
+fn follow_prints_appended_lines_until_interrupted() {
+	// the process id keeps two test runs from sharing, and truncating, one log
+	let name = format!("minigrep_follow_until_interrupted_{}.log", process::id());
+	let path = env::temp_dir().join(name);
+	fs::write(&path, "boot ok\nfirst error\n").unwrap();
+	
+	let mut child = common::command()
+		.args(["-F", "-n", "error"])
+		.arg(&path)
+		.stdout(Stdio::piped())
+		.spawn()
+		.unwrap();
+	let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+	
+	// by the time a line is out, following (and the Ctrl-C handler) has begun
+	assert_eq!("2:first error", lines.next().unwrap().unwrap());
+	
+	let writer = {
+		let path = path.clone();
+		thread::spawn(move || {
+			let mut log = OpenOptions::new().append(true).open(&path).unwrap();
+			for line in ["all fine\n", "second error\n", "third "] {
+				log.write_all(line.as_bytes()).unwrap();
+				thread::sleep(Duration::from_millis(50));
+			}
+			log.write_all(b"error\n").unwrap();
+		})
+	};
+	writer.join().unwrap();
+	
+	assert_eq!("4:second error", lines.next().unwrap().unwrap());
+	assert_eq!("5:third error", lines.next().unwrap().unwrap());
+	
+	let interrupted = Command::new("kill")
+		.args(["-INT", &child.id().to_string()])
+		.status()
+		.unwrap();
+	assert!(interrupted.success());
+	
+	// a clean finish: no more output, and grep's status for having matched
+	assert!(lines.next().is_none());
+	assert_eq!(Some(0), child.wait().unwrap().code());
+	
+	fs::remove_file(&path).unwrap();
+}
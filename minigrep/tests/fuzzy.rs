@@ -0,0 +1,34 @@
+mod common;
+
+fn minigrep(args: &[&str]) -> String {
+	let output = common::minigrep_in_crate(args);
+	
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn typos_still_match() {
+	assert_eq!("How dreary to be somebody!\n", minigrep(&["--fuzzy", "1", "somebdy", "poem.txt"]));
+	assert_eq!("To an admiring bog!\n", minigrep(&["--fuzzy=2", "-i", "ADMIRNG BOG", "poem.txt"]));
+}
+
+#[test]
+fn verbose_shows_distances() {
+	let output = minigrep(&["--verbose", "--fuzzy", "1", "-n", "nobdy", "poem.txt"]);
+	
+	// the command line is the only source of settings the scrubbed environment leaves
+	assert_eq!(
+		"Settings from command line: --verbose --fuzzy=1 -n\n\
+		Searching for nobdy\n\
+		Allowing up to 1 edit(s)\n\
+		In file poem.txt\n\
+		1:~1:I'm nobody! Who are you?\n\
+		3:~1:Are you nobody, too?\n",
+		output
+	);
+	
+	// an exact match is no edits at all
+	let output = minigrep(&["--verbose", "--fuzzy", "1", "frog", "poem.txt"]);
+	assert!(output.ends_with("~0:How public, like a frog\n"), "{output}");
+}
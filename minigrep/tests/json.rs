@@ -0,0 +1,90 @@
+
+use serde_json::Value;
+
+mod common;
+
+fn minigrep_json(args: &[&str]) -> Vec<Value> {
+	let output = common::minigrep_in_crate(&[&["--json"], args].concat());
+	
+	// 1 only means nothing matched, which still prints a summary
+	assert_ne!(Some(2), output.status.code(), "{}", String::from_utf8_lossy(&output.stderr));
+	String::from_utf8(output.stdout)
+		.unwrap()
+		.lines()
+		.map(|line| serde_json::from_str(line).expect("every line is one JSON object"))
+		.collect()
+}
+
+#[test]
+fn events_round_trip_against_poem() {
+	let poem = include_str!("../poem.txt");
+	let events = minigrep_json(&["-i", "how", "poem.txt"]);
+	let kinds: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+	
+	assert_eq!(vec!["begin", "match", "match", "end", "summary"], kinds);
+	assert_eq!("poem.txt", events[0]["path"]);
+	
+	for event in &events[1..3] {
+		// the reported position has to point at exactly this line of the poem
+		let number = event["line_number"].as_u64().unwrap() as usize;
+		let offset = event["byte_offset"].as_u64().unwrap() as usize;
+		let line = event["line"].as_str().unwrap();
+		
+		assert_eq!(Some(line), poem.lines().nth(number - 1));
+		assert!(poem[offset..].starts_with(line));
+		
+		let submatches = event["submatches"].as_array().unwrap();
+		assert_eq!(1, submatches.len());
+		let start = submatches[0]["start"].as_u64().unwrap() as usize;
+		let end = submatches[0]["end"].as_u64().unwrap() as usize;
+		assert_eq!("How", &line[start..end]);
+		assert_eq!("How", submatches[0]["text"]);
+	}
+	
+	assert_eq!(11, events[1]["line_number"]);
+	assert_eq!(13, events[2]["line_number"]);
+	assert_eq!(2, events[3]["matched_lines"]);
+	assert_eq!(1, events[4]["searched"]);
+	assert_eq!(1, events[4]["files_with_matches"]);
+	assert_eq!(2, events[4]["matched_lines"]);
+}
+
+#[test]
+fn context_and_summary_over_files() {
+	let events = minigrep_json(&["-B", "2", "frog", "poem.txt", "poem.txt"]);
+	let kinds: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+	
+	assert_eq!(
+		vec![
+			"begin", "context", "context", "match", "end",
+			"begin", "context", "context", "match", "end",
+			"summary",
+		],
+		kinds
+	);
+	assert_eq!(11, events[1]["line_number"]);
+	assert_eq!("How dreary to be somebody!", events[1]["line"]);
+	assert!(events[1]["submatches"].as_array().unwrap().is_empty());
+	assert_eq!(2, events[10]["searched"]);
+	assert_eq!(2, events[10]["matched_lines"]);
+}
+
+#[test]
+fn no_matches_still_reports() {
+	let events = minigrep_json(&["zebra", "poem.txt"]);
+	
+	assert_eq!(3, events.len());
+	assert_eq!(0, events[1]["matched_lines"]);
+	assert_eq!(0, events[2]["files_with_matches"]);
+}
+
+#[test]
+fn verbose_stays_out_of_the_stream() {
+	// every line of stdout is still JSON, minigrep_json checks as much
+	let events = minigrep_json(&["--verbose", "frog", "poem.txt"]);
+	assert_eq!(4, events.len());
+	
+	let output = common::minigrep_in_crate(&["--json", "--verbose", "frog", "poem.txt"]);
+	let stderr = String::from_utf8(output.stderr).unwrap();
+	assert!(stderr.contains("Searching for frog\n"), "{stderr}");
+}
@@ -0,0 +1,71 @@
+use std::io;
+use std::path::Path;
+
+use minigrep::{Config, Finish, PatternMatcher, Searcher, Sink, SinkMatch};
+
+/*
+ * minigrep used as a library, the way another tool would: a Matcher from
+ * the usual options, a Searcher over it, and a Sink of our own collecting
+ * what comes out instead of anything being printed.
+ */
+
+// a matching line's number, its text and where in it the matches were
+type Found = (usize, String, Vec<(usize, usize)>);
+
+#[derive(Default)]
+struct Collect {
+	lines: Vec<Found>,
+	finished: Vec<(String, usize)>,
+}
+
+impl Sink for Collect {
+	fn on_match(&mut self, found: &SinkMatch) -> io::Result<()> {
+		let text = String::from_utf8_lossy(found.line.bytes).into_owned();
+		self.lines.push((found.line.line_number, text, found.spans()));
+		Ok(())
+	}
+	
+	fn on_finish(&mut self, finish: &Finish) -> io::Result<()> {
+		self.finished.push((finish.path.to_string(), finish.matched_lines));
+		Ok(())
+	}
+}
+
+fn matcher(args: &[&str]) -> PatternMatcher {
+	// only these options, never whatever the user running the tool has set
+	let config = Config::from_args(
+		["minigrep"].iter().chain(args).map(|arg| arg.to_string())
+	).unwrap();
+	PatternMatcher::new(&config).unwrap()
+}
+
+#[test]
+fn collects_matches_from_a_file() {
+	let matcher = matcher(&["-i", "-w", "frog"]);
+	let searcher = Searcher::new(&matcher);
+	let mut collect = Collect::default();
+	
+	let count = searcher.search_path(Path::new("poem.txt"), &mut collect).unwrap();
+	
+	assert_eq!(1, count);
+	assert_eq!(vec![(13, String::from("How public, like a frog"), vec![(19, 23)])], collect.lines);
+	assert_eq!(vec![(String::from("poem.txt"), 1)], collect.finished);
+}
+
+#[test]
+fn one_sink_over_several_inputs() {
+	let matcher = matcher(&["body"]);
+	let mut searcher = Searcher::new(&matcher);
+	searcher.max_count = Some(1);
+	let mut collect = Collect::default();
+	
+	searcher.search_reader("first", &b"nobody\nsomebody\n"[..], &mut collect).unwrap();
+	searcher.search_reader("second", &b"anybody?\n"[..], &mut collect).unwrap();
+	
+	let lines: Vec<usize> = collect.lines.iter().map(|(number, _, _)| *number).collect();
+	assert_eq!(vec![1, 1], lines);
+	assert_eq!(
+		vec![(String::from("first"), 1), (String::from("second"), 1)],
+		collect.finished
+	);
+}
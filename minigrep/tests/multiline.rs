@@ -0,0 +1,60 @@
+mod common;
+
+fn minigrep(args: &[&str], stdin: &str) -> String {
+	let output = common::minigrep_with_stdin(args, stdin);
+	String::from_utf8(output.stdout).unwrap()
+}
+
+const LOG: &str = "\
+INFO start
+ERROR failed to open config
+  at load (config.rs:10)
+  at main (main.rs:3)
+INFO retrying
+ERROR failed to open config
+  at load (config.rs:10)
+INFO done
+";
+
+#[test]
+fn matches_span_lines() {
+	// a literal newline in PATTERN, the way a shell's $'...' gives one
+	assert_eq!(
+		"2:ERROR failed to open config\n3:  at load (config.rs:10)\n\
+		6:ERROR failed to open config\n7:  at load (config.rs:10)\n",
+		minigrep(&["-U", "-n", "config\n  at load"], LOG)
+	);
+	
+	// or \n in a regex, and lines no match touches are left out
+	assert_eq!(
+		"INFO retrying\nERROR failed to open config\n",
+		minigrep(&["-U", "-E", r"retrying\nERROR"], LOG)
+	);
+	
+	// without -U there's no line that holds both
+	assert_eq!("", minigrep(&["-E", r"retrying\nERROR"], LOG));
+}
+
+#[test]
+fn counting_matches() {
+	// six matches, over four lines
+	assert_eq!("6\n", minigrep(&["-U", "-c", "-E", "ERROR|config"], LOG));
+	assert_eq!("4\n", minigrep(&["-c", "-E", "ERROR|config"], LOG));
+	
+	// one match that covers four lines is still one
+	assert_eq!("1\n", minigrep(&["-U", "-c", "-E", r"(?s)start.*retrying"], LOG));
+	// with -v it's the lines no match touches that get counted
+	assert_eq!("3\n", minigrep(&["-U", "-v", "-c", "-E", r"(?s)start.*retrying"], LOG));
+}
+
+#[test]
+fn context_and_anchors() {
+	// ^ and $ still mean the start and end of a line
+	assert_eq!("INFO retrying\n", minigrep(&["-U", "-E", r"^INFO \w+ing$"], LOG));
+	assert_eq!("", minigrep(&["-U", "-E", r"retrying$ERROR"], LOG));
+	
+	assert_eq!(
+		"INFO retrying\nERROR failed to open config\n  at load (config.rs:10)\nINFO done\n",
+		minigrep(&["-U", "-A", "2", "-E", r"g\nERROR"], LOG)
+	);
+}
@@ -0,0 +1,67 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+mod common;
+
+/* use `cargo test --test parallel -- --show-output` to see the timings */
+
+fn make_tree(name: &str) -> PathBuf {
+	// enough files of uneven size that workers finish out of order
+	let root = env::temp_dir().join(name);
+	let _ = fs::remove_dir_all(&root);
+	let poem = include_str!("../poem.txt");
+	
+	for dir in 0..8 {
+		let dir_path = root.join(format!("dir{dir}"));
+		fs::create_dir_all(&dir_path).unwrap();
+		
+		for file in 0..25 {
+			let copies = 1 + (dir * 25 + file) % 40;
+			fs::write(dir_path.join(format!("poem{file}.txt")), poem.repeat(copies)).unwrap();
+		}
+	}
+	
+	root
+}
+
+fn minigrep(args: &[&str], root: &Path) -> (String, Duration) {
+	let start = Instant::now();
+	let output = common::command().args(args).arg(root).output().unwrap();
+	let elapsed = start.elapsed();
+	
+	assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+	(String::from_utf8(output.stdout).unwrap(), elapsed)
+}
+
+fn compare(name: &str, args: &[&str]) {
+	let root = make_tree(name);
+	let single: Vec<&str> = [&["-j", "1"], args].concat();
+	let multi: Vec<&str> = [&["-j", "4"], args].concat();
+	
+	let (expected, single_time) = minigrep(&single, &root);
+	let (found, multi_time) = minigrep(&multi, &root);
+	println!("{args:?}: 1 thread {single_time:?}, 4 threads {multi_time:?}");
+	
+	assert!(!expected.is_empty());
+	assert_eq!(expected, found);
+	
+	fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn threads_match_single_thread() {
+	compare("minigrep_threads_match", &["-r", "-n", "-i", "how"]);
+}
+
+#[test]
+fn threads_match_single_thread_count() {
+	compare("minigrep_threads_count", &["-r", "-c", "nobody"]);
+}
+
+#[test]
+fn threads_match_single_thread_context() {
+	// the "--" between groups has to survive being split across workers
+	compare("minigrep_threads_context", &["-r", "-C", "1", "frog"]);
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+mod common;
+use common::minigrep;
+
+const LOG: &str = "\
+12:00:01 started
+12:00:02 error: disk full
+12:00:03 retrying
+";
+
+fn scratch(name: &str) -> PathBuf {
+	// the process id keeps two test runs from writing over each other's files
+	env::temp_dir().join(format!("minigrep_{}_{name}", process::id()))
+}
+
+fn gzip_fixture(name: &str, contents: &str) -> PathBuf {
+	// a rotated log, the way logrotate leaves them
+	let path = scratch(name);
+	let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+	encoder.write_all(contents.as_bytes()).unwrap();
+	fs::write(&path, encoder.finish().unwrap()).unwrap();
+	path
+}
+
+#[test]
+fn searches_inside_gzip() {
+	let path = gzip_fixture("searches_inside_gzip.log.gz", LOG);
+	let name = path.to_str().unwrap();
+	
+	let output = minigrep(&["-z", "-n", "error", name]);
+	assert!(output.status.success());
+	assert_eq!(b"2:12:00:02 error: disk full\n", &output.stdout[..]);
+	
+	// without -z it's only a binary file, the text is nowhere to be seen
+	let output = minigrep(&["error", name]);
+	assert_eq!(Some(1), output.status.code());
+	
+	fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn names_the_compressed_file() {
+	let zipped = gzip_fixture("names_the_compressed_file.log.1.gz", LOG);
+	let plain = scratch("names_the_compressed_file.log");
+	fs::write(&plain, "12:01:00 error: disk still full\n").unwrap();
+	let (zipped, plain) = (zipped.to_str().unwrap(), plain.to_str().unwrap());
+	
+	// plain files are searched as they are
+	let output = minigrep(&["-z", "-c", "error", zipped, plain]);
+	assert_eq!(format!("{zipped}:1\n{plain}:1\n").as_bytes(), &output.stdout[..]);
+	
+	fs::remove_file(zipped).unwrap();
+	fs::remove_file(plain).unwrap();
+}
+
+#[test]
+fn corrupt_archives_fail_alone() {
+	let good = gzip_fixture("corrupt_archives_fail_alone.1.gz", LOG);
+	let bad = scratch("corrupt_archives_fail_alone.2.gz");
+	let mut bytes = fs::read(&good).unwrap();
+	bytes.truncate(bytes.len() / 2);
+	fs::write(&bad, bytes).unwrap();
+	let (good, bad) = (good.to_str().unwrap(), bad.to_str().unwrap());
+	
+	// the broken one is reported, and the search goes on to the next
+	let output = minigrep(&["-z", "retrying", bad, good]);
+	assert_eq!(Some(2), output.status.code());
+	assert_eq!(format!("{good}:12:00:03 retrying\n").as_bytes(), &output.stdout[..]);
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains(&format!("minigrep: {bad}: gzip: ")), "{stderr}");
+	
+	fs::remove_file(good).unwrap();
+	fs::remove_file(bad).unwrap();
+}